@@ -0,0 +1,109 @@
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use rust_week_3_exercises::*;
+use std::hint::black_box;
+
+fn sample_tx(input_count: usize) -> BitcoinTransaction {
+    let inputs = (0..input_count)
+        .map(|i| {
+            let mut txid = [0u8; 32];
+            txid[..8].copy_from_slice(&(i as u64).to_le_bytes());
+            // Roughly the size of a P2PKH scriptSig: signature plus compressed pubkey
+            TransactionInput::new(
+                OutPoint::new(txid, i as u32),
+                Script::new(vec![0x30; 107]),
+                0xFFFFFFFF,
+            )
+        })
+        .collect();
+    BitcoinTransaction::new(2, inputs, 0)
+}
+
+fn bench_transaction(c: &mut Criterion) {
+    let mut group = c.benchmark_group("transaction");
+    for input_count in [1, 100] {
+        let tx = sample_tx(input_count);
+        let bytes = tx.to_bytes();
+        group.throughput(Throughput::Bytes(bytes.len() as u64));
+
+        group.bench_with_input(
+            BenchmarkId::new("from_bytes", input_count),
+            &bytes,
+            |b, bytes| b.iter(|| BitcoinTransaction::from_bytes(black_box(bytes)).unwrap()),
+        );
+        group.bench_with_input(BenchmarkId::new("to_bytes", input_count), &tx, |b, tx| {
+            b.iter(|| black_box(tx).to_bytes())
+        });
+    }
+    group.finish();
+}
+
+fn bench_block_parse(c: &mut Criterion) {
+    // Roughly a block's worth of data: 2,000 transactions with two inputs each
+    let block: Vec<u8> = (0..2000).flat_map(|_| sample_tx(2).to_bytes()).collect();
+    let parse_all = |bytes: &[u8], borrowed: bool| {
+        let mut cursor = 0;
+        while cursor < bytes.len() {
+            let used = if borrowed {
+                let (tx, used) = TransactionRef::from_bytes(&bytes[cursor..]).unwrap();
+                black_box(tx);
+                used
+            } else {
+                let (tx, used) = BitcoinTransaction::from_bytes(&bytes[cursor..]).unwrap();
+                black_box(tx);
+                used
+            };
+            cursor += used;
+        }
+    };
+
+    let mut group = c.benchmark_group("block");
+    group.throughput(Throughput::Bytes(block.len() as u64));
+    group.bench_function("owned", |b| b.iter(|| parse_all(black_box(&block), false)));
+    group.bench_function("borrowed", |b| {
+        b.iter(|| parse_all(black_box(&block), true))
+    });
+    group.finish();
+}
+
+fn bench_small_inputs(c: &mut Criterion) {
+    // 1,000 inputs with 1-2 byte scriptSigs, where per-input decoding overhead dominates
+    let inputs = (0..1000)
+        .map(|i| {
+            TransactionInput::new(
+                OutPoint::new([i as u8; 32], i),
+                Script::new(vec![0x51; i as usize % 2 + 1]),
+                0xFFFFFFFF,
+            )
+        })
+        .collect();
+    let tx = BitcoinTransaction::new(2, inputs, 0);
+    let bytes = tx.to_bytes();
+    // Skip the version and the 3-byte input count; stop before the lock_time
+    let input_bytes = &bytes[7..bytes.len() - 4];
+
+    let mut group = c.benchmark_group("small_inputs");
+    group.throughput(Throughput::Bytes(bytes.len() as u64));
+    group.bench_function("input_from_bytes", |b| {
+        b.iter(|| {
+            let input_bytes = black_box(input_bytes);
+            let mut cursor = 0;
+            while cursor < input_bytes.len() {
+                let (input, used) = TransactionInput::from_bytes(&input_bytes[cursor..]).unwrap();
+                black_box(input);
+                cursor += used;
+            }
+        })
+    });
+    group.bench_function("tx_from_bytes", |b| {
+        b.iter(|| BitcoinTransaction::from_bytes(black_box(&bytes)).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_transaction,
+    bench_block_parse,
+    bench_small_inputs
+);
+criterion_main!(benches);
@@ -0,0 +1,64 @@
+//! Decodes a hex-encoded transaction and prints it.
+//!
+//! ```text
+//! cargo run --example decode -- tx.hex
+//! echo 0100000000... | cargo run --example decode
+//! ```
+
+use rust_week_3_exercises::BitcoinTransaction;
+use std::io::Read;
+use std::process::ExitCode;
+use std::{env, fs, io};
+
+fn read_input() -> io::Result<String> {
+    match env::args().nth(1) {
+        Some(path) => fs::read_to_string(path),
+        None => {
+            let mut input = String::new();
+            io::stdin().read_to_string(&mut input)?;
+            Ok(input)
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let input = match read_input() {
+        Ok(input) => input,
+        Err(e) => {
+            eprintln!("error: failed to read input: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let bytes = match hex::decode(input.trim()) {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            eprintln!("error: invalid hex: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let tx = match BitcoinTransaction::from_bytes(&bytes) {
+        Ok((tx, used)) if used == bytes.len() => tx,
+        Ok((_, used)) => {
+            eprintln!(
+                "error: {} trailing bytes after transaction",
+                bytes.len() - used
+            );
+            return ExitCode::FAILURE;
+        }
+        Err(e) => {
+            eprintln!("error: failed to decode transaction: {:?}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    // Display truncates long scripts; print them in full here
+    let mut out = String::new();
+    tx.write_display(&mut out, true)
+        .expect("writing to a String cannot fail");
+    print!("{}", out);
+    // Txids are conventionally displayed in reversed byte order
+    let mut txid = tx.txid().0;
+    txid.reverse();
+    println!("Txid: {}", hex::encode(txid));
+    ExitCode::SUCCESS
+}
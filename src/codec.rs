@@ -0,0 +1,19 @@
+use crate::{BitcoinError, CompactSize};
+
+/// Reads a CompactSize-prefixed UTF-8 string, returning it with the number of bytes consumed.
+pub fn read_var_str(bytes: &[u8]) -> Result<(String, usize), BitcoinError> {
+    let (len, prefix_len) = CompactSize::from_bytes(bytes)?;
+    let len = usize::try_from(len.value).map_err(|_| BitcoinError::InvalidFormat)?;
+    if bytes.len() - prefix_len < len {
+        return Err(BitcoinError::InsufficientBytes);
+    }
+    let s = std::str::from_utf8(&bytes[prefix_len..prefix_len + len])
+        .map_err(|_| BitcoinError::InvalidFormat)?;
+    Ok((s.to_string(), prefix_len + len))
+}
+
+pub fn write_var_str(s: &str) -> Vec<u8> {
+    let mut out = CompactSize::new(s.len() as u64).to_bytes();
+    out.extend(s.as_bytes());
+    out
+}
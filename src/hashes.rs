@@ -0,0 +1,51 @@
+use ripemd::Ripemd160;
+use sha2::{Digest, Sha256};
+
+pub fn sha256(data: &[u8]) -> [u8; 32] {
+    Sha256::digest(data).into()
+}
+
+/// SHA256 applied twice, as used for txids, block hashes and checksums.
+pub fn sha256d(data: &[u8]) -> [u8; 32] {
+    sha256(&sha256(data))
+}
+
+/// RIPEMD160 of SHA256, as used for P2PKH and P2SH addresses.
+pub fn hash160(data: &[u8]) -> [u8; 20] {
+    Ripemd160::digest(sha256(data)).into()
+}
+
+/// BIP-340 tagged hash: `SHA256(SHA256(tag) || SHA256(tag) || msg)`.
+pub fn tagged_hash(tag: &str, msg: &[u8]) -> [u8; 32] {
+    let tag_hash = sha256(tag.as_bytes());
+    Sha256::new()
+        .chain_update(tag_hash)
+        .chain_update(tag_hash)
+        .chain_update(msg)
+        .finalize()
+        .into()
+}
+
+/// Computes a block-style merkle root over `txids` (internal byte order), duplicating the last
+/// entry of any level with an odd number of nodes. Returns `None` for an empty slice.
+pub fn merkle_root(txids: &[[u8; 32]]) -> Option<[u8; 32]> {
+    if txids.is_empty() {
+        return None;
+    }
+    let mut level = txids.to_vec();
+    while level.len() > 1 {
+        if level.len() % 2 == 1 {
+            level.push(level[level.len() - 1]);
+        }
+        level = level
+            .chunks(2)
+            .map(|pair| {
+                let mut buf = [0u8; 64];
+                buf[..32].copy_from_slice(&pair[0]);
+                buf[32..].copy_from_slice(&pair[1]);
+                sha256d(&buf)
+            })
+            .collect();
+    }
+    Some(level[0])
+}
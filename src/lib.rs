@@ -1,7 +1,23 @@
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::fmt;
+use std::io;
 use std::ops::Deref;
 
+pub mod codec;
+pub mod hashes;
+pub mod opcodes;
+pub mod p2p;
+pub mod signatures;
+
+use opcodes::*;
+
+pub const MAX_SCRIPT_SIZE: usize = 10_000;
+pub const MAX_PUBKEYS_PER_MULTISIG: usize = 20;
+pub const DISPLAY_SCRIPT_BYTES: usize = 64;
+/// Largest OP_RETURN payload relayed by default policy.
+pub const MAX_OP_RETURN_DATA: usize = 80;
+
 #[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
 pub struct CompactSize {
     pub value: u64,
@@ -18,6 +34,11 @@ impl CompactSize {
         Self { value }
     }
 
+    /// True for 0xFD, 0xFE and 0xFF, the markers that introduce a multi-byte encoding.
+    pub fn is_prefix_byte(b: u8) -> bool {
+        b >= 0xFD
+    }
+
     pub fn to_bytes(&self) -> Vec<u8> {
         let v = self.value;
         if v <= 0xFC {
@@ -37,6 +58,12 @@ impl CompactSize {
         }
     }
 
+    pub fn consensus_encode<W: io::Write>(&self, w: &mut W) -> io::Result<usize> {
+        let bytes = self.to_bytes();
+        w.write_all(&bytes)?;
+        Ok(bytes.len())
+    }
+
     pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
         if bytes.is_empty() {
             return Err(BitcoinError::InsufficientBytes);
@@ -69,11 +96,75 @@ impl CompactSize {
             }
         }
     }
+
+    pub fn checked_add(&self, other: u64) -> Option<CompactSize> {
+        self.value.checked_add(other).map(Self::new)
+    }
+
+    /// Decodes the CompactSize at the start of `bytes` and reports whether it used the shortest
+    /// encoding for its value.
+    pub fn bytes_are_canonical(bytes: &[u8]) -> Result<bool, BitcoinError> {
+        let (size, used) = Self::from_bytes(bytes)?;
+        Ok(size.to_bytes().len() == used)
+    }
+
+    /// Decodes like `from_bytes` but rejects values above `max` with `InvalidFormat`.
+    pub fn from_bytes_max(bytes: &[u8], max: u64) -> Result<(Self, usize), BitcoinError> {
+        let (size, used) = Self::from_bytes(bytes)?;
+        if size.value > max {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        Ok((size, used))
+    }
 }
 
-#[derive(Debug, PartialEq, Eq, Clone)]
+impl PartialEq<u64> for CompactSize {
+    fn eq(&self, other: &u64) -> bool {
+        self.value == *other
+    }
+}
+
+impl PartialEq<CompactSize> for u64 {
+    fn eq(&self, other: &CompactSize) -> bool {
+        *self == other.value
+    }
+}
+
+/// ```
+/// use rust_week_3_exercises::CompactSize;
+///
+/// assert_eq!(format!("{}", CompactSize::new(253)), "253");
+/// ```
+impl fmt::Display for CompactSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.value, f)
+    }
+}
+
+/// ```
+/// use rust_week_3_exercises::CompactSize;
+///
+/// assert_eq!(format!("{:x}", CompactSize::new(253)), "fd");
+/// assert_eq!(format!("{:#06x}", CompactSize::new(253)), "0x00fd");
+/// ```
+impl fmt::LowerHex for CompactSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::LowerHex::fmt(&self.value, f)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
 pub struct Txid(pub [u8; 32]);
 
+impl Txid {
+    pub const ZERO: Txid = Txid([0u8; 32]);
+
+    /// Returns true for the all-zero txid (coinbase prevout).
+    pub fn is_null(&self) -> bool {
+        *self == Txid::ZERO
+    }
+}
+
 impl Serialize for Txid {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -99,7 +190,71 @@ impl<'de> Deserialize<'de> for Txid {
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, PartialOrd, Ord)]
+pub struct Vout(pub u32);
+
+impl From<u32> for Vout {
+    fn from(value: u32) -> Self {
+        Vout(value)
+    }
+}
+
+impl From<Vout> for u32 {
+    fn from(vout: Vout) -> Self {
+        vout.0
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, PartialOrd, Ord)]
+pub struct Sequence(pub u32);
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum RelativeLockTime {
+    Blocks(u16),
+    /// In units of 512 seconds.
+    Time(u16),
+}
+
+impl Sequence {
+    pub const MAX: Sequence = Sequence(0xFFFFFFFF);
+    const DISABLE_FLAG: u32 = 1 << 31;
+    const TYPE_FLAG: u32 = 1 << 22;
+
+    pub fn is_final(&self) -> bool {
+        *self == Self::MAX
+    }
+
+    pub fn enables_rbf(&self) -> bool {
+        self.0 < 0xFFFFFFFE
+    }
+
+    /// The BIP-68 relative lock-time, or `None` when the disable flag is set.
+    pub fn relative_locktime(&self) -> Option<RelativeLockTime> {
+        if self.0 & Self::DISABLE_FLAG != 0 {
+            return None;
+        }
+        let value = self.0 as u16;
+        if self.0 & Self::TYPE_FLAG != 0 {
+            Some(RelativeLockTime::Time(value))
+        } else {
+            Some(RelativeLockTime::Blocks(value))
+        }
+    }
+}
+
+impl From<u32> for Sequence {
+    fn from(value: u32) -> Self {
+        Sequence(value)
+    }
+}
+
+impl From<Sequence> for u32 {
+    fn from(sequence: Sequence) -> Self {
+        sequence.0
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Serialize, Deserialize)]
 pub struct OutPoint {
     pub txid: Txid,
     pub vout: u32,
@@ -114,10 +269,32 @@ impl OutPoint {
     }
 
     pub fn to_bytes(&self) -> Vec<u8> {
-        let mut out = Vec::new();
-        out.extend(&self.txid.0);
-        out.extend(&self.vout.to_le_bytes());
-        out
+        self.to_key().to_vec()
+    }
+
+    pub fn vout(&self) -> Vout {
+        Vout(self.vout)
+    }
+
+    /// Fixed-size wire form (txid followed by little-endian vout), suitable as a UTXO-set key.
+    pub fn to_key(&self) -> [u8; 36] {
+        let mut key = [0u8; 36];
+        key[..32].copy_from_slice(&self.txid.0);
+        key[32..].copy_from_slice(&self.vout.to_le_bytes());
+        key
+    }
+
+    pub fn from_key(key: &[u8; 36]) -> OutPoint {
+        let mut txid = [0u8; 32];
+        txid.copy_from_slice(&key[..32]);
+        let vout = u32::from_le_bytes(key[32..].try_into().unwrap());
+        Self::new(txid, vout)
+    }
+
+    pub fn consensus_encode<W: io::Write>(&self, w: &mut W) -> io::Result<usize> {
+        w.write_all(&self.txid.0)?;
+        w.write_all(&self.vout.to_le_bytes())?;
+        Ok(36)
     }
 
     pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
@@ -147,17 +324,347 @@ impl Script {
         out
     }
 
+    pub fn consensus_encode<W: io::Write>(&self, w: &mut W) -> io::Result<usize> {
+        let len = CompactSize::new(self.bytes.len() as u64).consensus_encode(w)?;
+        w.write_all(&self.bytes)?;
+        Ok(len + self.bytes.len())
+    }
+
     pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
-        let (len_prefix, len_bytes) = CompactSize::from_bytes(bytes)?;
-        let total_len = len_prefix.value as usize;
-        if bytes.len() < len_bytes + total_len {
-            return Err(BitcoinError::InsufficientBytes);
+        Self::from_bytes_at(bytes, 0)
+    }
+
+    /// Decodes a length-prefixed script starting at `offset` and returns the offset just past
+    /// it, so callers walking a larger buffer can keep a single cursor.
+    pub(crate) fn from_bytes_at(
+        bytes: &[u8],
+        offset: usize,
+    ) -> Result<(Self, usize), BitcoinError> {
+        let (data, end) = script_slice_at(bytes, offset)?;
+        Ok((Self::new(data.to_vec()), end))
+    }
+
+    pub fn instructions(&self) -> Instructions<'_> {
+        Instructions {
+            bytes: &self.bytes,
+            pos: 0,
+        }
+    }
+
+    pub fn to_minimal(&self) -> Result<Script, BitcoinError> {
+        let mut out = Vec::with_capacity(self.bytes.len());
+        for instruction in self.instructions() {
+            match instruction? {
+                Instruction::Push { data, .. } => push_minimal(&mut out, data),
+                Instruction::Op(op) => out.push(op),
+            }
         }
-        let data = bytes[len_bytes..len_bytes + total_len].to_vec();
-        Ok((Self::new(data), len_bytes + total_len))
+        Ok(Script::new(out))
+    }
+
+    pub fn is_minimal(&self) -> bool {
+        self.instructions().all(|instruction| match instruction {
+            Ok(Instruction::Push { opcode, data }) => is_minimal_push(opcode, data),
+            Ok(Instruction::Op(_)) => true,
+            Err(_) => false,
+        })
+    }
+
+    /// True if any push could have used a shorter opcode, including pushes of a single byte
+    /// 1..=16 or 0x81 that should have been OP_1..OP_16 or OP_1NEGATE.
+    pub fn has_nonminimal_pushes(&self) -> bool {
+        self.instructions().any(|instruction| {
+            matches!(instruction, Ok(Instruction::Push { opcode, data }) if !is_minimal_push(opcode, data))
+        })
+    }
+
+    /// True if every opcode is a data push or a small-number opcode up to OP_16, as standardness
+    /// requires of scriptSigs.
+    pub fn is_push_only(&self) -> bool {
+        self.instructions().all(|instruction| match instruction {
+            Ok(Instruction::Push { .. }) => true,
+            Ok(Instruction::Op(op)) => op <= OP_16,
+            Err(_) => false,
+        })
+    }
+
+    /// Parses space-separated assembly such as `OP_DUP OP_HASH160 <hex> OP_EQUALVERIFY
+    /// OP_CHECKSIG`. Bare hex tokens become minimal pushes; the `OP_PUSHDATA` names are rejected
+    /// because on their own they would leave a push without its length and data.
+    pub fn from_asm(asm: &str) -> Result<Script, BitcoinError> {
+        let mut out = Vec::new();
+        for token in asm.split_whitespace() {
+            if token.starts_with("OP_") {
+                match opcodes::opcode_from_name(token) {
+                    Some(OP_PUSHDATA1 | OP_PUSHDATA2 | OP_PUSHDATA4) | None => {
+                        return Err(BitcoinError::InvalidFormat);
+                    }
+                    Some(op) => out.push(op),
+                }
+            } else {
+                let data = hex::decode(token).map_err(|_| BitcoinError::InvalidFormat)?;
+                push_minimal(&mut out, &data);
+            }
+        }
+        Ok(Script::new(out))
+    }
+
+    /// Disassembles the script, printing pushes as hex and empty pushes of any encoding as
+    /// `OP_0`. A malformed trailing push is shown as `[error]`.
+    pub fn to_asm(&self) -> String {
+        let mut tokens = Vec::new();
+        for instruction in self.instructions() {
+            match instruction {
+                Ok(Instruction::Push { data: [], .. }) => tokens.push("OP_0".to_string()),
+                Ok(Instruction::Push { data, .. }) => tokens.push(hex::encode(data)),
+                Ok(Instruction::Op(op)) => tokens.push(opcodes::opcode_name(op).to_string()),
+                Err(_) => tokens.push("[error]".to_string()),
+            }
+        }
+        tokens.join(" ")
+    }
+
+    /// Returns the witness version and program for `OP_n <2..=40 byte push>` scripts.
+    pub fn witness_program(&self) -> Option<(u8, &[u8])> {
+        let bytes = &self.bytes;
+        if bytes.len() < 4 || bytes.len() > 42 || bytes[1] as usize + 2 != bytes.len() {
+            return None;
+        }
+        let version = match bytes[0] {
+            OP_0 => 0,
+            op @ OP_1..=OP_16 => op - OP_1 + 1,
+            _ => return None,
+        };
+        Some((version, &bytes[2..]))
+    }
+
+    /// The hash a standard output pays to: 20 bytes for P2PKH, P2SH and P2WPKH, or 32 bytes for
+    /// P2WSH and P2TR.
+    pub fn pubkey_hash(&self) -> Option<Vec<u8>> {
+        match self.bytes.as_slice() {
+            [
+                OP_DUP,
+                OP_HASH160,
+                0x14,
+                hash @ ..,
+                OP_EQUALVERIFY,
+                OP_CHECKSIG,
+            ] if hash.len() == 20 => Some(hash.to_vec()),
+            [OP_HASH160, 0x14, hash @ .., OP_EQUAL] if hash.len() == 20 => Some(hash.to_vec()),
+            _ => match self.witness_program()? {
+                (0, program) if program.len() == 20 || program.len() == 32 => {
+                    Some(program.to_vec())
+                }
+                (1, program) if program.len() == 32 => Some(program.to_vec()),
+                _ => None,
+            },
+        }
+    }
+
+    /// Counts signature operations. In legacy mode every OP_CHECKMULTISIG(VERIFY) costs
+    /// MAX_PUBKEYS_PER_MULTISIG; in accurate mode it costs N when directly preceded by OP_N.
+    /// Counting stops at the first malformed push, as Bitcoin Core does.
+    pub fn sigop_count(&self, accurate: bool) -> usize {
+        let mut count = 0;
+        let mut last_op = None;
+        for instruction in self.instructions() {
+            let Ok(instruction) = instruction else { break };
+            if let Instruction::Op(op) = instruction {
+                match op {
+                    OP_CHECKSIG | OP_CHECKSIGVERIFY => count += 1,
+                    OP_CHECKMULTISIG | OP_CHECKMULTISIGVERIFY => match last_op {
+                        Some(n @ OP_1..=OP_16) if accurate => count += (n - OP_1 + 1) as usize,
+                        _ => count += MAX_PUBKEYS_PER_MULTISIG,
+                    },
+                    _ => {}
+                }
+                last_op = Some(op);
+            } else {
+                last_op = None;
+            }
+        }
+        count
+    }
+
+    /// Returns the pushes of a push-only scriptSig that are strict DER signatures followed by a
+    /// standard sighash byte.
+    pub fn extract_signatures(&self) -> Vec<Vec<u8>> {
+        if !self.is_push_only() {
+            return Vec::new();
+        }
+        self.instructions()
+            .filter_map(|instruction| match instruction {
+                Ok(Instruction::Push { data, .. }) if is_signature_shaped(data) => {
+                    Some(data.to_vec())
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The scriptCode for legacy signing: everything after the last OP_CODESEPARATOR, or the
+    /// whole script if there is none. This assumes every OP_CODESEPARATOR is executed, which
+    /// holds for scripts without conditional branches around them.
+    pub fn script_code_for_signing(&self) -> Script {
+        let mut instructions = self.instructions();
+        let mut start = 0;
+        while let Some(Ok(instruction)) = instructions.next() {
+            if instruction == Instruction::Op(OP_CODESEPARATOR) {
+                start = instructions.pos;
+            }
+        }
+        Script::new(self.bytes[start..].to_vec())
+    }
+
+    /// Compares the scripts' instruction sequences with every OP_NOP removed. Nothing else is
+    /// ignored: push encodings, OP_NOP1..OP_NOP10 and all other opcodes must match exactly.
+    /// Malformed scripts are only equal if their bytes are identical.
+    pub fn semantically_equal(&self, other: &Script) -> bool {
+        match (
+            self.instructions_without_nops(),
+            other.instructions_without_nops(),
+        ) {
+            (Ok(a), Ok(b)) => a == b,
+            _ => self == other,
+        }
+    }
+
+    fn instructions_without_nops(&self) -> Result<Vec<Instruction<'_>>, BitcoinError> {
+        self.instructions()
+            .filter(|instruction| *instruction != Ok(Instruction::Op(OP_NOP)))
+            .collect()
+    }
+
+    /// True for scripts that can never be satisfied and may be pruned from a UTXO set.
+    pub fn is_provably_unspendable(&self) -> bool {
+        self.bytes.first() == Some(&OP_RETURN) || self.bytes.len() > MAX_SCRIPT_SIZE
+    }
+
+    /// Splits `data` into `OP_RETURN <push>` scripts of at most `max_per_output` bytes each,
+    /// typically `MAX_OP_RETURN_DATA`. Every chunk is a data push, even a single byte that
+    /// `OP_1`..`OP_16` could encode. Empty data or a `max_per_output` of 0 yields no scripts.
+    pub fn op_return_chunks(data: &[u8], max_per_output: usize) -> Vec<Script> {
+        if max_per_output == 0 {
+            return Vec::new();
+        }
+        data.chunks(max_per_output)
+            .map(|chunk| {
+                let mut out = vec![OP_RETURN];
+                push_data(&mut out, chunk);
+                Script::new(out)
+            })
+            .collect()
     }
 }
 
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Instruction<'a> {
+    /// A data push, including `OP_0`, together with the opcode that encoded it.
+    Push {
+        opcode: u8,
+        data: &'a [u8],
+    },
+    Op(u8),
+}
+
+pub struct Instructions<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Iterator for Instructions<'a> {
+    type Item = Result<Instruction<'a>, BitcoinError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let opcode = *self.bytes.get(self.pos)?;
+        self.pos += 1;
+        let width = match opcode {
+            OP_PUSHDATA1 => 1,
+            OP_PUSHDATA2 => 2,
+            OP_PUSHDATA4 => 4,
+            0x00..=0x4b => 0,
+            _ => return Some(Ok(Instruction::Op(opcode))),
+        };
+
+        let rest = &self.bytes[self.pos..];
+        if rest.len() < width {
+            self.pos = self.bytes.len();
+            return Some(Err(BitcoinError::InvalidFormat));
+        }
+        let mut len_bytes = [0u8; 4];
+        len_bytes[..width].copy_from_slice(&rest[..width]);
+        let len = match width {
+            0 => opcode as usize,
+            _ => u32::from_le_bytes(len_bytes) as usize,
+        };
+        if rest.len() - width < len {
+            self.pos = self.bytes.len();
+            return Some(Err(BitcoinError::InvalidFormat));
+        }
+
+        let data = &rest[width..width + len];
+        self.pos += width + len;
+        Some(Ok(Instruction::Push { opcode, data }))
+    }
+}
+
+/// Borrows the length-prefixed script starting at `offset`, returning it with the offset just
+/// past it. This is the zero-copy form of `Script::from_bytes_at`, used by `TransactionInputRef`.
+fn script_slice_at(bytes: &[u8], offset: usize) -> Result<(&[u8], usize), BitcoinError> {
+    let (len_prefix, len_bytes) = CompactSize::from_bytes(&bytes[offset..])?;
+    let start = offset + len_bytes;
+    let total_len = usize::try_from(len_prefix.value).map_err(|_| BitcoinError::InvalidFormat)?;
+    if bytes.len() - start < total_len {
+        return Err(BitcoinError::InsufficientBytes);
+    }
+    Ok((&bytes[start..start + total_len], start + total_len))
+}
+
+fn is_signature_shaped(data: &[u8]) -> bool {
+    signatures::is_valid_der_signature(data)
+        && signatures::sighash_type(data).is_some_and(signatures::is_standard_sighash)
+}
+
+fn is_minimal_push(opcode: u8, data: &[u8]) -> bool {
+    match data {
+        [] => opcode == OP_0,
+        [1..=16] | [0x81] => false,
+        _ if data.len() <= 0x4b => opcode as usize == data.len(),
+        _ if data.len() <= 0xFF => opcode == OP_PUSHDATA1,
+        _ if data.len() <= 0xFFFF => opcode == OP_PUSHDATA2,
+        _ => opcode == OP_PUSHDATA4,
+    }
+}
+
+/// Appends `data` to `out` using the shortest push encoding allowed by BIP-62.
+fn push_minimal(out: &mut Vec<u8>, data: &[u8]) {
+    match data {
+        [] => out.push(OP_0),
+        [n @ 1..=16] => out.push(OP_1 + n - 1),
+        [0x81] => out.push(OP_1NEGATE),
+        _ => push_data(out, data),
+    }
+}
+
+/// Appends `data` to `out` as a direct push or PUSHDATA, never substituting `OP_1`..`OP_16`
+/// or `OP_1NEGATE` for single-byte data.
+fn push_data(out: &mut Vec<u8>, data: &[u8]) {
+    let len = data.len();
+    if len <= 0x4b {
+        out.push(len as u8);
+    } else if len <= 0xFF {
+        out.push(OP_PUSHDATA1);
+        out.push(len as u8);
+    } else if len <= 0xFFFF {
+        out.push(OP_PUSHDATA2);
+        out.extend(&(len as u16).to_le_bytes());
+    } else {
+        out.push(OP_PUSHDATA4);
+        out.extend(&(len as u32).to_le_bytes());
+    }
+    out.extend(data);
+}
+
 impl Deref for Script {
     type Target = Vec<u8>;
     fn deref(&self) -> &Self::Target {
@@ -181,6 +688,24 @@ impl TransactionInput {
         }
     }
 
+    pub fn sequence(&self) -> Sequence {
+        Sequence(self.sequence)
+    }
+
+    pub fn outpoint_bytes(&self) -> [u8; 36] {
+        self.previous_output.to_key()
+    }
+
+    /// The sighash flag of each signature in the scriptSig. Witness-signed inputs have empty
+    /// scriptSigs and so yield no flags.
+    pub fn signing_flags(&self) -> Vec<u32> {
+        self.script_sig
+            .extract_signatures()
+            .iter()
+            .filter_map(|sig| signatures::sighash_type(sig))
+            .collect()
+    }
+
     pub fn to_bytes(&self) -> Vec<u8> {
         let mut out = self.previous_output.to_bytes();
         out.extend(self.script_sig.to_bytes());
@@ -188,21 +713,21 @@ impl TransactionInput {
         out
     }
 
+    pub fn consensus_encode<W: io::Write>(&self, w: &mut W) -> io::Result<usize> {
+        let mut len = self.previous_output.consensus_encode(w)?;
+        len += self.script_sig.consensus_encode(w)?;
+        w.write_all(&self.sequence.to_le_bytes())?;
+        Ok(len + 4)
+    }
+
     pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
         let (prev_out, prev_len) = OutPoint::from_bytes(bytes)?;
-        let (script, script_len) = Script::from_bytes(&bytes[prev_len..])?;
-        if bytes.len() < prev_len + script_len + 4 {
+        let (script, cursor) = Script::from_bytes_at(bytes, prev_len)?;
+        if bytes.len() < cursor + 4 {
             return Err(BitcoinError::InsufficientBytes);
         }
-        let sequence = u32::from_le_bytes(
-            bytes[prev_len + script_len..prev_len + script_len + 4]
-                .try_into()
-                .unwrap(),
-        );
-        Ok((
-            Self::new(prev_out, script, sequence),
-            prev_len + script_len + 4,
-        ))
+        let sequence = u32::from_le_bytes(bytes[cursor..cursor + 4].try_into().unwrap());
+        Ok((Self::new(prev_out, script, sequence), cursor + 4))
     }
 }
 
@@ -233,41 +758,538 @@ impl BitcoinTransaction {
         out
     }
 
+    pub fn consensus_encode<W: io::Write>(&self, w: &mut W) -> io::Result<usize> {
+        w.write_all(&self.version.to_le_bytes())?;
+        let mut len = 4;
+        len += CompactSize::new(self.inputs.len() as u64).consensus_encode(w)?;
+        for input in &self.inputs {
+            len += input.consensus_encode(w)?;
+        }
+        w.write_all(&self.lock_time.to_le_bytes())?;
+        Ok(len + 4)
+    }
+
     pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
-        if bytes.len() < 4 {
+        Self::from_bytes_diagnostic(bytes).map_err(|(e, _)| e)
+    }
+
+    /// The serialization prefixed with its length as a CompactSize, making a stream of
+    /// transactions self-delimiting.
+    pub fn to_framed(&self) -> Vec<u8> {
+        let body = self.to_bytes();
+        let mut out = CompactSize::new(body.len() as u64).to_bytes();
+        out.extend(body);
+        out
+    }
+
+    pub fn from_framed(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        let (frame_len, prefix_len) = CompactSize::from_bytes(bytes)?;
+        let frame_len =
+            usize::try_from(frame_len.value).map_err(|_| BitcoinError::InvalidFormat)?;
+        if bytes.len() - prefix_len < frame_len {
+            return Err(BitcoinError::InsufficientBytes);
+        }
+        let (tx, used) = Self::from_bytes(&bytes[prefix_len..prefix_len + frame_len])?;
+        if used != frame_len {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        Ok((tx, prefix_len + frame_len))
+    }
+
+    /// Parses the known transaction layout and returns how many bytes it used, leaving any
+    /// trailing bytes for the caller to interpret. Use this when walking a larger buffer or
+    /// experimenting with appended fields; use `from_bytes_exact` when `bytes` should hold
+    /// exactly one transaction, such as a decoded hex string.
+    pub fn from_bytes_lenient(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        Self::from_bytes(bytes)
+    }
+
+    /// Parses a transaction that must span all of `bytes`, rejecting trailing data with
+    /// `InvalidFormat`.
+    pub fn from_bytes_exact(bytes: &[u8]) -> Result<Self, BitcoinError> {
+        let (tx, used) = Self::from_bytes(bytes)?;
+        if used != bytes.len() {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        Ok(tx)
+    }
+
+    /// Parses like `from_bytes`, then rejects transactions whose scriptSigs are not push-only
+    /// with `InvalidFormat`. This enforces a relay policy rule, not a consensus rule.
+    pub fn from_bytes_standard(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        let (tx, used) = Self::from_bytes(bytes)?;
+        if !tx
+            .inputs
+            .iter()
+            .all(|input| input.script_sig.is_push_only())
+        {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        Ok((tx, used))
+    }
+
+    /// Like `from_bytes`, but on failure also reports the offset of the field that could not
+    /// be decoded.
+    pub fn from_bytes_diagnostic(bytes: &[u8]) -> Result<(Self, usize), (BitcoinError, usize)> {
+        let (tx, used) = TransactionRef::from_bytes_diagnostic(bytes)?;
+        Ok((tx.to_owned(), used))
+    }
+
+    /// Like `from_bytes`, but also reports how many bytes each field of the encoding took.
+    pub fn from_bytes_annotated(bytes: &[u8]) -> Result<(Self, TransactionLayout), BitcoinError> {
+        let mut layout = TransactionLayout {
+            version: 4,
+            input_count: 0,
+            inputs: Vec::new(),
+            lock_time: 4,
+            total: 0,
+        };
+        let (tx, _) = TransactionRef::decode(bytes, Some(&mut layout)).map_err(|(e, _)| e)?;
+        Ok((tx.to_owned(), layout))
+    }
+
+    /// Lazily decodes back-to-back transactions from `bytes`, stopping after the first error.
+    pub fn iter_from_bytes(bytes: &[u8]) -> TxIter<'_> {
+        TxIter { bytes, pos: 0 }
+    }
+
+    /// The version as Bitcoin Core interprets it: a signed 32-bit integer over the same bytes.
+    pub fn version_i32(&self) -> i32 {
+        self.version as i32
+    }
+
+    /// Double-SHA256 of the serialized transaction, in internal byte order.
+    pub fn txid(&self) -> Txid {
+        Txid(hashes::sha256d(&self.to_bytes()))
+    }
+
+    /// A malleation-resistant identifier: the txid of this transaction with every scriptSig
+    /// emptied. This is not the consensus txid.
+    pub fn normalized_txid(&self) -> Txid {
+        self.to_unsigned().txid()
+    }
+
+    /// A copy with every scriptSig emptied, keeping outpoints, sequences, version and
+    /// lock_time: the template collaborators sign independently. Because the legacy txid
+    /// commits to scriptSigs, the unsigned form has a different txid from any signed one.
+    pub fn to_unsigned(&self) -> BitcoinTransaction {
+        let mut unsigned = self.clone();
+        for input in &mut unsigned.inputs {
+            input.script_sig.bytes.clear();
+        }
+        unsigned
+    }
+
+    pub fn with_lock_time(&self, lock_time: u32) -> BitcoinTransaction {
+        Self {
+            lock_time,
+            ..self.clone()
+        }
+    }
+
+    pub fn with_version(&self, version: u32) -> BitcoinTransaction {
+        Self {
+            version,
+            ..self.clone()
+        }
+    }
+
+    pub fn to_hex(&self) -> String {
+        hex::encode(self.to_bytes())
+    }
+
+    /// Streams the lowercase hex serialization into `w` without building the byte buffer first.
+    pub fn write_hex<W: fmt::Write>(&self, w: &mut W) -> fmt::Result {
+        write_hex_bytes(w, &self.version.to_le_bytes())?;
+        write_hex_bytes(w, &CompactSize::new(self.inputs.len() as u64).to_bytes())?;
+        for input in &self.inputs {
+            write_hex_bytes(w, &input.previous_output.txid.0)?;
+            write_hex_bytes(w, &input.previous_output.vout.to_le_bytes())?;
+            let script = &input.script_sig.bytes;
+            write_hex_bytes(w, &CompactSize::new(script.len() as u64).to_bytes())?;
+            write_hex_bytes(w, script)?;
+            write_hex_bytes(w, &input.sequence.to_le_bytes())?;
+        }
+        write_hex_bytes(w, &self.lock_time.to_le_bytes())
+    }
+
+    /// Compact JSON in the fixed field order of `RpcTransaction`, suitable for hashing and
+    /// snapshot fixtures.
+    pub fn to_canonical_json_string(&self) -> String {
+        serde_json::to_string(&RpcTransaction::from(self)).expect("RpcTransaction serializes")
+    }
+
+    /// Streams the `Display` form into `w`. Unless `verbose` is set, scriptSigs longer than
+    /// `DISPLAY_SCRIPT_BYTES` are shown as a hex prefix followed by `...`.
+    pub fn write_display<W: fmt::Write>(&self, w: &mut W, verbose: bool) -> fmt::Result {
+        writeln!(w, "Version: {}", self.version)?;
+        for input in &self.inputs {
+            let script = &input.script_sig.bytes;
+            writeln!(w, "Previous Output Vout: {}", input.previous_output.vout)?;
+            writeln!(w, "ScriptSig Length: {}", script.len())?;
+            write!(w, "ScriptSig: ")?;
+            if verbose || script.len() <= DISPLAY_SCRIPT_BYTES {
+                write_hex_bytes(w, script)?;
+            } else {
+                write_hex_bytes(w, &script[..DISPLAY_SCRIPT_BYTES])?;
+                write!(w, "...")?;
+            }
+            writeln!(w)?;
+            writeln!(w, "Sequence: {}", input.sequence)?;
+        }
+        writeln!(w, "Lock Time: {}", self.lock_time)
+    }
+
+    pub fn hex_dump(&self) -> String {
+        hex_dump(&self.to_bytes())
+    }
+
+    /// Legacy sigop count over all scriptSigs, the figure relay policy limits.
+    pub fn sigop_count(&self) -> usize {
+        self.inputs
+            .iter()
+            .map(|input| input.script_sig.sigop_count(false))
+            .sum()
+    }
+
+    /// True if both transactions have the same version, lock_time and multiset of inputs,
+    /// regardless of input order.
+    pub fn equivalent_unordered(&self, other: &BitcoinTransaction) -> bool {
+        if self.version != other.version
+            || self.lock_time != other.lock_time
+            || self.inputs.len() != other.inputs.len()
+        {
+            return false;
+        }
+        let sorted_inputs = |tx: &BitcoinTransaction| {
+            let mut inputs: Vec<Vec<u8>> = tx.inputs.iter().map(|input| input.to_bytes()).collect();
+            inputs.sort();
+            inputs
+        };
+        sorted_inputs(self) == sorted_inputs(other)
+    }
+
+    pub fn spent_outpoints(&self) -> Vec<&OutPoint> {
+        self.inputs
+            .iter()
+            .map(|input| &input.previous_output)
+            .collect()
+    }
+
+    /// True if both transactions spend at least one common outpoint.
+    pub fn conflicts_with(&self, other: &BitcoinTransaction) -> bool {
+        let spent: HashSet<&OutPoint> = self.spent_outpoints().into_iter().collect();
+        other
+            .spent_outpoints()
+            .iter()
+            .any(|outpoint| spent.contains(outpoint))
+    }
+
+    pub fn sequences(&self) -> Vec<u32> {
+        self.inputs.iter().map(|input| input.sequence).collect()
+    }
+
+    pub fn min_sequence(&self) -> Option<u32> {
+        self.inputs.iter().map(|input| input.sequence).min()
+    }
+
+    pub fn all_final(&self) -> bool {
+        self.inputs.iter().all(|input| input.sequence == 0xFFFFFFFF)
+    }
+
+    /// True for version 2+ transactions with at least one BIP-68 relative lock-time.
+    pub fn uses_csv(&self) -> bool {
+        self.version >= 2
+            && self
+                .inputs
+                .iter()
+                .any(|input| input.sequence().relative_locktime().is_some())
+    }
+
+    /// BIP-125: a transaction signals replaceability if any input has a sequence below 0xFFFFFFFE.
+    pub fn is_rbf(&self) -> bool {
+        self.inputs.iter().any(|input| input.sequence < 0xFFFFFFFE)
+    }
+
+    pub fn signal_rbf(&mut self) {
+        for input in &mut self.inputs {
+            if input.sequence >= 0xFFFFFFFE {
+                input.sequence = 0xFFFFFFFD;
+            }
+        }
+    }
+
+    /// Points each input whose previous output appears in `mapping` at the mapped outpoint.
+    pub fn remap_prevouts(&mut self, mapping: &HashMap<OutPoint, OutPoint>) {
+        for input in &mut self.inputs {
+            if let Some(new) = mapping.get(&input.previous_output) {
+                input.previous_output = new.clone();
+            }
+        }
+    }
+
+    pub fn input_bytes(&self, index: usize) -> Option<Vec<u8>> {
+        self.inputs.get(index).map(TransactionInput::to_bytes)
+    }
+
+    pub fn take_inputs(&self, n: usize) -> BitcoinTransaction {
+        let n = n.min(self.inputs.len());
+        Self::new(self.version, self.inputs[..n].to_vec(), self.lock_time)
+    }
+
+    pub fn clear_rbf(&mut self) {
+        for input in &mut self.inputs {
+            input.sequence = 0xFFFFFFFF;
+        }
+    }
+}
+
+/// Bytes consumed by each field of a decoded transaction, as returned by
+/// `BitcoinTransaction::from_bytes_annotated`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct TransactionLayout {
+    pub version: usize,
+    /// Length of the input count CompactSize.
+    pub input_count: usize,
+    /// Length of each input, in order.
+    pub inputs: Vec<usize>,
+    pub lock_time: usize,
+    pub total: usize,
+}
+
+/// A transaction input whose scriptSig borrows from the buffer it was decoded from.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct TransactionInputRef<'a> {
+    pub previous_output: OutPoint,
+    pub script_sig: &'a [u8],
+    pub sequence: u32,
+}
+
+impl<'a> TransactionInputRef<'a> {
+    pub fn from_bytes(bytes: &'a [u8]) -> Result<(Self, usize), BitcoinError> {
+        let (previous_output, prev_len) = OutPoint::from_bytes(bytes)?;
+        let (script_sig, cursor) = script_slice_at(bytes, prev_len)?;
+        if bytes.len() < cursor + 4 {
             return Err(BitcoinError::InsufficientBytes);
         }
+        let sequence = u32::from_le_bytes(bytes[cursor..cursor + 4].try_into().unwrap());
+        let input = Self {
+            previous_output,
+            script_sig,
+            sequence,
+        };
+        Ok((input, cursor + 4))
+    }
+
+    pub fn to_owned(&self) -> TransactionInput {
+        TransactionInput::new(
+            self.previous_output.clone(),
+            Script::new(self.script_sig.to_vec()),
+            self.sequence,
+        )
+    }
+}
+
+/// A zero-copy view of a transaction: every script borrows from the decoded buffer, which
+/// avoids copying script data when only reading large batches of transactions.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct TransactionRef<'a> {
+    pub version: u32,
+    pub inputs: Vec<TransactionInputRef<'a>>,
+    pub lock_time: u32,
+}
+
+impl<'a> TransactionRef<'a> {
+    pub fn from_bytes(bytes: &'a [u8]) -> Result<(Self, usize), BitcoinError> {
+        Self::from_bytes_diagnostic(bytes).map_err(|(e, _)| e)
+    }
+
+    pub(crate) fn from_bytes_diagnostic(
+        bytes: &'a [u8],
+    ) -> Result<(Self, usize), (BitcoinError, usize)> {
+        Self::decode(bytes, None)
+    }
+
+    /// The single decoding walk, optionally recording the length of each field in `layout`.
+    fn decode(
+        bytes: &'a [u8],
+        mut layout: Option<&mut TransactionLayout>,
+    ) -> Result<(Self, usize), (BitcoinError, usize)> {
+        if bytes.len() < 4 {
+            return Err((BitcoinError::InsufficientBytes, 0));
+        }
 
         let version = u32::from_le_bytes(bytes[0..4].try_into().unwrap());
-        let (input_count, mut offset) = CompactSize::from_bytes(&bytes[4..])?;
+        let (input_count, mut offset) = CompactSize::from_bytes(&bytes[4..]).map_err(|e| (e, 4))?;
         offset += 4; // adjust for version prefix
+        if let Some(layout) = layout.as_deref_mut() {
+            layout.input_count = offset - 4;
+        }
 
+        let input_count =
+            usize::try_from(input_count.value).map_err(|_| (BitcoinError::InvalidFormat, 4))?;
         let mut inputs = Vec::new();
         let mut cursor = offset;
-        for _ in 0..input_count.value {
-            let (input, used) = TransactionInput::from_bytes(&bytes[cursor..])?;
+        for _ in 0..input_count {
+            let (input, used) =
+                TransactionInputRef::from_bytes(&bytes[cursor..]).map_err(|e| (e, cursor))?;
             inputs.push(input);
             cursor += used;
+            if let Some(layout) = layout.as_deref_mut() {
+                layout.inputs.push(used);
+            }
         }
 
         if bytes.len() < cursor + 4 {
-            return Err(BitcoinError::InsufficientBytes);
+            return Err((BitcoinError::InsufficientBytes, cursor));
+        }
+        if let Some(layout) = layout {
+            layout.total = cursor + 4;
         }
 
         let lock_time = u32::from_le_bytes(bytes[cursor..cursor + 4].try_into().unwrap());
-        Ok((Self::new(version, inputs, lock_time), cursor + 4))
+        let tx = Self {
+            version,
+            inputs,
+            lock_time,
+        };
+        Ok((tx, cursor + 4))
+    }
+
+    pub fn to_owned(&self) -> BitcoinTransaction {
+        BitcoinTransaction::new(
+            self.version,
+            self.inputs
+                .iter()
+                .map(TransactionInputRef::to_owned)
+                .collect(),
+            self.lock_time,
+        )
+    }
+}
+
+pub struct TxIter<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Iterator for TxIter<'_> {
+    type Item = Result<BitcoinTransaction, BitcoinError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pos >= self.bytes.len() {
+            return None;
+        }
+        match BitcoinTransaction::from_bytes(&self.bytes[self.pos..]) {
+            Ok((tx, used)) => {
+                self.pos += used;
+                Some(Ok(tx))
+            }
+            Err(e) => {
+                self.pos = self.bytes.len();
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// JSON shape of a transaction as returned by bitcoind's `getrawtransaction <txid> true`.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct RpcTransaction {
+    pub version: u32,
+    pub locktime: u32,
+    pub vin: Vec<RpcInput>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct RpcInput {
+    pub txid: String,
+    pub vout: u32,
+    #[serde(rename = "scriptSig")]
+    pub script_sig: RpcScriptSig,
+    pub sequence: u32,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct RpcScriptSig {
+    pub hex: String,
+}
+
+impl From<&BitcoinTransaction> for RpcTransaction {
+    fn from(tx: &BitcoinTransaction) -> Self {
+        let vin = tx
+            .inputs
+            .iter()
+            .map(|input| {
+                // bitcoind displays txids in reversed byte order
+                let mut txid = input.previous_output.txid.0;
+                txid.reverse();
+                RpcInput {
+                    txid: hex::encode(txid),
+                    vout: input.previous_output.vout,
+                    script_sig: RpcScriptSig {
+                        hex: hex::encode(&input.script_sig.bytes),
+                    },
+                    sequence: input.sequence,
+                }
+            })
+            .collect();
+        Self {
+            version: tx.version,
+            locktime: tx.lock_time,
+            vin,
+        }
     }
 }
 
 impl fmt::Display for BitcoinTransaction {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        writeln!(f, "Version: {}", self.version)?;
-        for input in &self.inputs {
-            writeln!(f, "Previous Output Vout: {}", input.previous_output.vout)?;
-            writeln!(f, "ScriptSig Length: {}", input.script_sig.bytes.len())?;
-            writeln!(f, "ScriptSig: {}", hex::encode(&input.script_sig.bytes))?;
-            writeln!(f, "Sequence: {}", input.sequence)?;
+        self.write_display(f, false)
+    }
+}
+
+fn write_hex_bytes<W: fmt::Write>(w: &mut W, bytes: &[u8]) -> fmt::Result {
+    for b in bytes {
+        write!(w, "{:02x}", b)?;
+    }
+    Ok(())
+}
+
+/// Formats `bytes` as offset-annotated lines of 16 bytes: hex on the left, ASCII on the right.
+pub fn hex_dump(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(16) * 78);
+    write_hex_dump(&mut out, bytes).expect("writing to a String cannot fail");
+    out
+}
+
+fn write_hex_dump<W: fmt::Write>(w: &mut W, bytes: &[u8]) -> fmt::Result {
+    for (i, chunk) in bytes.chunks(16).enumerate() {
+        write!(w, "{:08x} ", i * 16)?;
+        for j in 0..16 {
+            match chunk.get(j) {
+                Some(b) => write!(w, " {:02x}", b)?,
+                None => w.write_str("   ")?,
+            }
+        }
+        w.write_str("  |")?;
+        for &b in chunk {
+            w.write_char(if b.is_ascii_graphic() || b == b' ' {
+                b as char
+            } else {
+                '.'
+            })?;
         }
-        writeln!(f, "Lock Time: {}", self.lock_time)
+        w.write_str("|\n")?;
+    }
+    Ok(())
+}
+
+/// Number of confirmations for a transaction mined at `tx_height` given the current `chain_tip`.
+/// A `tx_height` of 0 means unconfirmed; a transaction in the tip block has one confirmation.
+/// A `tx_height` above `chain_tip` is in a block not yet seen, so it also has none.
+pub fn confirmations(tx_height: u32, chain_tip: u32) -> u32 {
+    if tx_height == 0 || tx_height > chain_tip {
+        return 0;
     }
+    chain_tip - tx_height + 1
 }
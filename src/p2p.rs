@@ -0,0 +1,113 @@
+use crate::{BitcoinError, BitcoinTransaction, CompactSize, hashes};
+
+pub const HEADER_LEN: usize = 24;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Network {
+    Mainnet,
+    Testnet,
+    Signet,
+    Regtest,
+}
+
+impl Network {
+    pub fn magic(&self) -> [u8; 4] {
+        match self {
+            Network::Mainnet => [0xF9, 0xBE, 0xB4, 0xD9],
+            Network::Testnet => [0x0B, 0x11, 0x09, 0x07],
+            Network::Signet => [0x0A, 0x03, 0xCF, 0x40],
+            Network::Regtest => [0xFA, 0xBF, 0xB5, 0xDA],
+        }
+    }
+}
+
+/// Validates the 24-byte message header (magic, command, length and checksum) and returns
+/// the payload it describes.
+fn parse_message<'a>(
+    bytes: &'a [u8],
+    command: &str,
+    network: Network,
+) -> Result<&'a [u8], BitcoinError> {
+    if bytes.len() < HEADER_LEN {
+        return Err(BitcoinError::InsufficientBytes);
+    }
+    if bytes[0..4] != network.magic() {
+        return Err(BitcoinError::InvalidFormat);
+    }
+    let name = &bytes[4..16];
+    let name_len = name.iter().position(|&b| b == 0).unwrap_or(name.len());
+    if &name[..name_len] != command.as_bytes() || name[name_len..].iter().any(|&b| b != 0) {
+        return Err(BitcoinError::InvalidFormat);
+    }
+    let length = u32::from_le_bytes(bytes[16..20].try_into().unwrap()) as usize;
+    if bytes.len() - HEADER_LEN < length {
+        return Err(BitcoinError::InsufficientBytes);
+    }
+    let payload = &bytes[HEADER_LEN..HEADER_LEN + length];
+    if bytes[20..24] != hashes::sha256d(payload)[..4] {
+        return Err(BitcoinError::InvalidFormat);
+    }
+    Ok(payload)
+}
+
+pub fn parse_tx_message(
+    bytes: &[u8],
+    network: Network,
+) -> Result<BitcoinTransaction, BitcoinError> {
+    let payload = parse_message(bytes, "tx", network)?;
+    let (tx, used) = BitcoinTransaction::from_bytes(payload)?;
+    if used != payload.len() {
+        return Err(BitcoinError::InvalidFormat);
+    }
+    Ok(tx)
+}
+
+pub fn encode_message(
+    command: &str,
+    payload: &[u8],
+    network: Network,
+) -> Result<Vec<u8>, BitcoinError> {
+    if command.len() > 12 || !command.is_ascii() {
+        return Err(BitcoinError::InvalidFormat);
+    }
+    let length = u32::try_from(payload.len()).map_err(|_| BitcoinError::InvalidFormat)?;
+
+    let mut out = Vec::with_capacity(HEADER_LEN + payload.len());
+    out.extend(network.magic());
+    let mut name = [0u8; 12];
+    name[..command.len()].copy_from_slice(command.as_bytes());
+    out.extend(name);
+    out.extend(length.to_le_bytes());
+    out.extend(&hashes::sha256d(payload)[..4]);
+    out.extend(payload);
+    Ok(out)
+}
+
+/// Decodes an `inv`/`getdata` payload into (type, hash) entries.
+pub fn parse_inv(bytes: &[u8]) -> Result<Vec<(u32, [u8; 32])>, BitcoinError> {
+    let (count, mut cursor) = CompactSize::from_bytes(bytes)?;
+    let count = usize::try_from(count.value).map_err(|_| BitcoinError::InvalidFormat)?;
+    let mut entries = Vec::new();
+    for _ in 0..count {
+        if bytes.len() - cursor < 36 {
+            return Err(BitcoinError::InsufficientBytes);
+        }
+        let kind = u32::from_le_bytes(bytes[cursor..cursor + 4].try_into().unwrap());
+        let hash: [u8; 32] = bytes[cursor + 4..cursor + 36].try_into().unwrap();
+        entries.push((kind, hash));
+        cursor += 36;
+    }
+    if cursor != bytes.len() {
+        return Err(BitcoinError::InvalidFormat);
+    }
+    Ok(entries)
+}
+
+pub fn encode_inv(entries: &[(u32, [u8; 32])]) -> Vec<u8> {
+    let mut out = CompactSize::new(entries.len() as u64).to_bytes();
+    for (kind, hash) in entries {
+        out.extend(kind.to_le_bytes());
+        out.extend(hash);
+    }
+    out
+}
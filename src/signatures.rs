@@ -0,0 +1,51 @@
+pub const SIGHASH_ALL: u32 = 0x01;
+pub const SIGHASH_NONE: u32 = 0x02;
+pub const SIGHASH_SINGLE: u32 = 0x03;
+pub const SIGHASH_ANYONECANPAY: u32 = 0x80;
+
+/// Checks strict DER encoding of a signature with its trailing sighash byte, following
+/// BIP-66: `0x30 <len> 0x02 <lenR> <R> 0x02 <lenS> <S> <sighash>` with minimally encoded,
+/// non-negative integers.
+pub fn is_valid_der_signature(sig: &[u8]) -> bool {
+    if sig.len() < 9 || sig.len() > 73 {
+        return false;
+    }
+    if sig[0] != 0x30 || sig[1] as usize != sig.len() - 3 {
+        return false;
+    }
+
+    let len_r = sig[3] as usize;
+    if 5 + len_r >= sig.len() {
+        return false;
+    }
+    let len_s = sig[5 + len_r] as usize;
+    if len_r + len_s + 7 != sig.len() {
+        return false;
+    }
+
+    is_valid_der_integer(&sig[2..4 + len_r])
+        && is_valid_der_integer(&sig[4 + len_r..6 + len_r + len_s])
+}
+
+/// `int` is `0x02 <len> <value>`: the value must be non-empty, non-negative and carry no
+/// unnecessary leading zero byte.
+fn is_valid_der_integer(int: &[u8]) -> bool {
+    let value = &int[2..];
+    int[0] == 0x02
+        && !value.is_empty()
+        && value[0] & 0x80 == 0
+        && !(value.len() > 1 && value[0] == 0x00 && value[1] & 0x80 == 0)
+}
+
+/// The sighash flag carried in the last byte of a signature.
+pub fn sighash_type(sig: &[u8]) -> Option<u32> {
+    sig.last().map(|&flag| flag as u32)
+}
+
+/// True for ALL, NONE and SINGLE, with or without ANYONECANPAY.
+pub fn is_standard_sighash(flag: u32) -> bool {
+    matches!(
+        flag & !SIGHASH_ANYONECANPAY,
+        SIGHASH_ALL | SIGHASH_NONE | SIGHASH_SINGLE
+    )
+}
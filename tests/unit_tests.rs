@@ -1,4 +1,5 @@
 use rust_week_3_exercises::*;
+use std::collections::HashMap;
 
 #[cfg(test)]
 mod tests {
@@ -110,4 +111,1652 @@ mod tests {
         assert!(output.contains("Lock Time: 0"));
         assert!(output.contains("Previous Output Vout: 7"));
     }
+
+    #[test]
+    fn test_hex_dump() {
+        let bytes: Vec<u8> = (0x30..0x42).collect();
+        let dump = hex_dump(&bytes);
+        let lines: Vec<&str> = dump.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(
+            lines[0],
+            "00000000  30 31 32 33 34 35 36 37 38 39 3a 3b 3c 3d 3e 3f  |0123456789:;<=>?|"
+        );
+        assert!(lines[1].starts_with("00000010  40 41   "));
+        assert!(lines[1].ends_with("|@A|"));
+        assert_eq!(hex_dump(&[]), "");
+
+        let tx = BitcoinTransaction::new(1, vec![], 0);
+        assert_eq!(tx.hex_dump(), hex_dump(&tx.to_bytes()));
+    }
+
+    #[test]
+    fn test_rpc_transaction_json() {
+        let input = TransactionInput::new(
+            OutPoint::new(dummy_txid(0xAB), 3),
+            Script::new(vec![0xDE, 0xAD]),
+            0xFFFFFFFE,
+        );
+        let tx = BitcoinTransaction::new(2, vec![input], 500);
+        let rpc = RpcTransaction::from(&tx);
+        let json = serde_json::to_value(&rpc).unwrap();
+
+        assert_eq!(json["version"], 2);
+        assert_eq!(json["locktime"], 500);
+        let vin = &json["vin"][0];
+        assert_eq!(
+            vin["txid"],
+            format!("ab{}", "00".repeat(31)).as_str(),
+            "txid must be displayed in reversed byte order"
+        );
+        assert_eq!(vin["vout"], 3);
+        assert_eq!(vin["scriptSig"]["hex"], "dead");
+        assert_eq!(vin["sequence"], 0xFFFFFFFEu32);
+    }
+
+    #[test]
+    fn test_script_to_minimal() {
+        // OP_PUSHDATA1 for 10 bytes, then OP_DUP
+        let mut bytes = vec![0x4c, 0x0a];
+        bytes.extend([0xAA; 10]);
+        bytes.push(0x76);
+        let script = Script::new(bytes);
+        assert!(!script.is_minimal());
+
+        let minimal = script.to_minimal().unwrap();
+        let mut expected = vec![0x0a];
+        expected.extend([0xAA; 10]);
+        expected.push(0x76);
+        assert_eq!(minimal.bytes, expected);
+        assert!(minimal.is_minimal());
+
+        // Single-byte pushes of small numbers become OP_N / OP_1NEGATE
+        let script = Script::new(vec![0x01, 0x05, 0x01, 0x81, 0x4c, 0x00]);
+        assert_eq!(script.to_minimal().unwrap().bytes, vec![0x55, 0x4f, 0x00]);
+
+        // Large pushes keep the shortest PUSHDATA form
+        let mut bytes = vec![0x4d, 0x00, 0x01];
+        bytes.extend([0x11; 256]);
+        let minimal = Script::new(bytes.clone()).to_minimal().unwrap();
+        assert_eq!(minimal.bytes, bytes);
+
+        // Truncated push
+        let broken = Script::new(vec![0x4c, 0x05, 0x01]);
+        assert_eq!(broken.to_minimal(), Err(BitcoinError::InvalidFormat));
+        assert!(!broken.is_minimal());
+    }
+
+    #[test]
+    fn test_bitcoin_tx_huge_input_count() {
+        let mut bytes = 1u32.to_le_bytes().to_vec();
+        bytes.extend(CompactSize::new(u64::MAX).to_bytes());
+        bytes.extend(0u32.to_le_bytes());
+
+        let expected = if cfg!(target_pointer_width = "64") {
+            BitcoinError::InsufficientBytes
+        } else {
+            BitcoinError::InvalidFormat
+        };
+        assert_eq!(BitcoinTransaction::from_bytes(&bytes), Err(expected));
+    }
+
+    #[test]
+    fn test_signal_and_clear_rbf() {
+        let inputs = vec![
+            TransactionInput::new(
+                OutPoint::new(dummy_txid(1), 0),
+                Script::new(vec![]),
+                0xFFFFFFFF,
+            ),
+            TransactionInput::new(
+                OutPoint::new(dummy_txid(2), 1),
+                Script::new(vec![]),
+                0xFFFFFFFE,
+            ),
+            TransactionInput::new(OutPoint::new(dummy_txid(3), 2), Script::new(vec![]), 10),
+        ];
+        let mut tx = BitcoinTransaction::new(2, inputs, 0);
+        tx.clear_rbf();
+        assert!(!tx.is_rbf());
+
+        tx.inputs[2].sequence = 10;
+        tx.signal_rbf();
+        assert!(tx.is_rbf());
+        assert_eq!(tx.inputs[0].sequence, 0xFFFFFFFD);
+        assert_eq!(tx.inputs[1].sequence, 0xFFFFFFFD);
+        assert_eq!(tx.inputs[2].sequence, 10);
+
+        tx.clear_rbf();
+        assert!(!tx.is_rbf());
+        assert!(tx.inputs.iter().all(|input| input.sequence == 0xFFFFFFFF));
+    }
+
+    #[test]
+    fn test_hashes() {
+        assert_eq!(
+            hex::encode(hashes::sha256d(b"")),
+            "5df6e0e2761359d30a8275058e299fcc0381534545f55cf43e41983f5d4c9456"
+        );
+        assert_eq!(
+            hex::encode(hashes::sha256d(b"hello")),
+            "9595c9df90075148eb06860365df33584b75bff782a510c6cd4883a419833d50"
+        );
+        assert_eq!(
+            hex::encode(hashes::hash160(b"")),
+            "b472a266d0bd89c13706a4132ccfb16f7c3b9fcb"
+        );
+    }
+
+    #[test]
+    fn test_script_is_provably_unspendable() {
+        let op_return = Script::new(vec![0x6a, 0x04, 0xDE, 0xAD, 0xBE, 0xEF]);
+        assert!(op_return.is_provably_unspendable());
+
+        let mut p2pkh = vec![0x76, 0xA9, 0x14];
+        p2pkh.extend([0x11; 20]);
+        p2pkh.extend([0x88, 0xAC]);
+        assert!(!Script::new(p2pkh).is_provably_unspendable());
+
+        let oversized = Script::new(vec![0x51; MAX_SCRIPT_SIZE + 1]);
+        assert!(oversized.is_provably_unspendable());
+        assert!(!Script::new(vec![]).is_provably_unspendable());
+    }
+
+    #[test]
+    fn test_take_inputs() {
+        let inputs: Vec<_> = (0..3)
+            .map(|i| {
+                TransactionInput::new(OutPoint::new(dummy_txid(i), 0), Script::new(vec![i]), 0)
+            })
+            .collect();
+        let tx = BitcoinTransaction::new(2, inputs.clone(), 77);
+
+        let prefix = tx.take_inputs(2);
+        assert_eq!(prefix.inputs, inputs[..2]);
+        assert_eq!(prefix.version, 2);
+        assert_eq!(prefix.lock_time, 77);
+
+        assert_eq!(tx.take_inputs(10), tx);
+        assert!(tx.take_inputs(0).inputs.is_empty());
+    }
+
+    #[test]
+    fn test_compact_size_is_prefix_byte() {
+        assert!(!CompactSize::is_prefix_byte(0x00));
+        assert!(!CompactSize::is_prefix_byte(0xFC));
+        assert!(CompactSize::is_prefix_byte(0xFD));
+        assert!(CompactSize::is_prefix_byte(0xFE));
+        assert!(CompactSize::is_prefix_byte(0xFF));
+    }
+
+    #[test]
+    fn test_write_hex_matches_to_hex() {
+        let inputs = vec![
+            TransactionInput::new(
+                OutPoint::new(dummy_txid(1), 0),
+                Script::new(vec![0xAB; 300]),
+                0xFFFFFFFF,
+            ),
+            TransactionInput::new(OutPoint::new(dummy_txid(2), 5), Script::new(vec![]), 1),
+        ];
+        let tx = BitcoinTransaction::new(2, inputs, 1000);
+
+        let mut streamed = String::new();
+        tx.write_hex(&mut streamed).unwrap();
+        assert_eq!(streamed, tx.to_hex());
+        assert_eq!(hex::decode(&streamed).unwrap(), tx.to_bytes());
+    }
+
+    #[test]
+    fn test_sequence_accessors() {
+        let inputs = vec![
+            TransactionInput::new(
+                OutPoint::new(dummy_txid(1), 0),
+                Script::new(vec![]),
+                0xFFFFFFFF,
+            ),
+            TransactionInput::new(OutPoint::new(dummy_txid(2), 0), Script::new(vec![]), 144),
+        ];
+        let mut tx = BitcoinTransaction::new(2, inputs, 0);
+        assert_eq!(tx.sequences(), vec![0xFFFFFFFF, 144]);
+        assert_eq!(tx.min_sequence(), Some(144));
+        assert!(!tx.all_final());
+
+        tx.inputs[1].sequence = 0xFFFFFFFF;
+        assert!(tx.all_final());
+
+        let empty = BitcoinTransaction::new(2, vec![], 0);
+        assert_eq!(empty.min_sequence(), None);
+        assert!(empty.all_final());
+    }
+
+    #[test]
+    fn test_script_length_beyond_usize_or_buffer() {
+        let bytes = CompactSize::new(u64::MAX).to_bytes();
+        let expected = if cfg!(target_pointer_width = "64") {
+            BitcoinError::InsufficientBytes
+        } else {
+            BitcoinError::InvalidFormat
+        };
+        assert_eq!(Script::from_bytes(&bytes), Err(expected));
+    }
+
+    #[cfg(target_pointer_width = "32")]
+    #[test]
+    fn test_script_length_overflows_usize() {
+        let bytes = CompactSize::new(u32::MAX as u64 + 1).to_bytes();
+        assert_eq!(Script::from_bytes(&bytes), Err(BitcoinError::InvalidFormat));
+    }
+
+    #[test]
+    fn test_merkle_root() {
+        let leaves: Vec<[u8; 32]> = (1..=4).map(|i| [i; 32]).collect();
+        let expected = [
+            "0101010101010101010101010101010101010101010101010101010101010101",
+            "39ce20bede82c96b8908bec4a157b09c549b3db90b9b474bda9ae9b9030310b4",
+            "223e023fadf1f053df26988871f893c821c28edf77d64a955e6c2a02d547bdac",
+            "085aabaef98668701b87c9a1986bdf116726a9949802326b69895697d4e8c812",
+        ];
+        for (n, root) in expected.iter().enumerate() {
+            let computed = hashes::merkle_root(&leaves[..n + 1]).unwrap();
+            assert_eq!(hex::encode(computed), *root, "{} leaves", n + 1);
+        }
+        assert_eq!(hashes::merkle_root(&[]), None);
+
+        // Block 170: the first block with a non-coinbase transaction
+        let reversed = |s: &str| {
+            let mut bytes: [u8; 32] = hex::decode(s).unwrap().try_into().unwrap();
+            bytes.reverse();
+            bytes
+        };
+        let txids = [
+            reversed("b1fea52486ce0c62bb442b530a3f0132b826c74e473d1f2c220bfa78111c5082"),
+            reversed("f4184fc596403b9d638783cf57adfe4c75c605f6356fbc91338530e9831e9e16"),
+        ];
+        assert_eq!(
+            hashes::merkle_root(&txids),
+            Some(reversed(
+                "7dac2c5666815c17a3b36427de37bb9d2e2c5ccec3f8633eb91a4205cb4c10ff"
+            ))
+        );
+    }
+
+    #[test]
+    fn test_conflicts_with() {
+        let spend = |outpoints: &[(u8, u32)]| {
+            let inputs = outpoints
+                .iter()
+                .map(|&(txid, vout)| {
+                    TransactionInput::new(
+                        OutPoint::new(dummy_txid(txid), vout),
+                        Script::new(vec![]),
+                        0xFFFFFFFF,
+                    )
+                })
+                .collect();
+            BitcoinTransaction::new(2, inputs, 0)
+        };
+        let a = spend(&[(1, 0), (2, 1)]);
+        let b = spend(&[(3, 0), (2, 1)]);
+        let c = spend(&[(1, 1), (2, 0)]);
+
+        assert!(a.conflicts_with(&b));
+        assert!(b.conflicts_with(&a));
+        assert!(!a.conflicts_with(&c));
+        assert_eq!(
+            a.spent_outpoints(),
+            vec![
+                &OutPoint::new(dummy_txid(1), 0),
+                &OutPoint::new(dummy_txid(2), 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_consensus_encode_matches_to_bytes() {
+        let input = TransactionInput::new(
+            OutPoint::new(dummy_txid(9), 4),
+            Script::new(vec![0x42; 260]),
+            0xFFFFFFFE,
+        );
+        let tx = BitcoinTransaction::new(2, vec![input.clone(), input.clone()], 1234);
+
+        let mut buf = Vec::new();
+        let written = tx.consensus_encode(&mut buf).unwrap();
+        assert_eq!(buf, tx.to_bytes());
+        assert_eq!(written, buf.len());
+
+        let mut buf = Vec::new();
+        assert_eq!(
+            input.consensus_encode(&mut buf).unwrap(),
+            input.to_bytes().len()
+        );
+        assert_eq!(buf, input.to_bytes());
+
+        let cs = CompactSize::new(70000);
+        let mut buf = Vec::new();
+        assert_eq!(cs.consensus_encode(&mut buf).unwrap(), 5);
+        assert_eq!(buf, cs.to_bytes());
+    }
+
+    #[test]
+    fn test_compact_size_eq_u64() {
+        let (cs, _) = CompactSize::from_bytes(&[0xFD, 0x00, 0x01]).unwrap();
+        assert_eq!(cs, 256u64);
+        assert_eq!(256u64, cs);
+        assert_ne!(cs, 255u64);
+    }
+
+    #[test]
+    fn test_iter_from_bytes() {
+        let txs: Vec<_> = (1..=3)
+            .map(|i| {
+                let input = TransactionInput::new(
+                    OutPoint::new(dummy_txid(i), i as u32),
+                    Script::new(vec![i; i as usize]),
+                    0xFFFFFFFF,
+                );
+                BitcoinTransaction::new(i as u32, vec![input], 0)
+            })
+            .collect();
+        let mut buf: Vec<u8> = txs.iter().flat_map(|tx| tx.to_bytes()).collect();
+
+        let parsed: Vec<_> = BitcoinTransaction::iter_from_bytes(&buf)
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(parsed, txs);
+
+        buf.truncate(buf.len() - 2);
+        let mut iter = BitcoinTransaction::iter_from_bytes(&buf);
+        assert_eq!(iter.next(), Some(Ok(txs[0].clone())));
+        assert_eq!(iter.next(), Some(Ok(txs[1].clone())));
+        assert_eq!(iter.next(), Some(Err(BitcoinError::InsufficientBytes)));
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn test_txid_is_null() {
+        assert!(Txid::ZERO.is_null());
+        assert_eq!(Txid::ZERO, Txid([0u8; 32]));
+        assert!(!Txid(dummy_txid(1)).is_null());
+
+        let mut first = [0u8; 32];
+        first[0] = 1;
+        assert!(!Txid(first).is_null());
+    }
+
+    #[test]
+    fn test_with_lock_time_and_version() {
+        let input = TransactionInput::new(
+            OutPoint::new(dummy_txid(1), 0),
+            Script::new(vec![0x01]),
+            0xFFFFFFFE,
+        );
+        let tx = BitcoinTransaction::new(1, vec![input], 0);
+
+        let locked = tx.with_lock_time(800_000);
+        assert_eq!(locked.lock_time, 800_000);
+        assert_eq!(locked.version, tx.version);
+        assert_eq!(locked.inputs, tx.inputs);
+        assert_ne!(locked.txid(), tx.txid());
+
+        let bumped = tx.with_version(2);
+        assert_eq!(bumped.version, 2);
+        assert_eq!(bumped.lock_time, tx.lock_time);
+        assert_eq!(bumped.inputs, tx.inputs);
+        assert_ne!(bumped.txid(), tx.txid());
+
+        assert_eq!(tx.with_version(1).txid(), tx.txid());
+    }
+
+    #[test]
+    fn test_script_has_nonminimal_pushes() {
+        let mut p2pkh_sig = vec![0x47];
+        p2pkh_sig.extend([0x30; 0x47]);
+        p2pkh_sig.push(0x21);
+        p2pkh_sig.extend([0x02; 0x21]);
+        let minimal = Script::new(p2pkh_sig);
+        assert!(!minimal.has_nonminimal_pushes());
+        assert!(minimal.is_minimal());
+
+        let cases = [
+            vec![0x4c, 0x01, 0xAA], // PUSHDATA1 for a single byte
+            vec![0x01, 0x07],       // should be OP_7
+            vec![0x01, 0x81],       // should be OP_1NEGATE
+            vec![0x4c, 0x00],       // should be OP_0
+            vec![0x4d, 0x02, 0x00, 0xAB, 0xCD],
+        ];
+        for bytes in cases {
+            let script = Script::new(bytes);
+            assert!(script.has_nonminimal_pushes(), "{:?}", script.bytes);
+            assert!(!script.is_minimal());
+        }
+
+        // Non-push opcodes and the empty push are already minimal
+        assert!(!Script::new(vec![0x00, 0x76, 0x51]).has_nonminimal_pushes());
+    }
+
+    #[test]
+    fn test_outpoint_key_roundtrip() {
+        let outpoint = OutPoint::new(dummy_txid(0xEE), 0x01020304);
+        let key = outpoint.to_key();
+        assert_eq!(&key[..32], &outpoint.txid.0);
+        assert_eq!(&key[32..], &[0x04, 0x03, 0x02, 0x01]);
+        assert_eq!(key.to_vec(), outpoint.to_bytes());
+        assert_eq!(OutPoint::from_key(&key), outpoint);
+    }
+
+    #[test]
+    fn test_script_is_push_only() {
+        let mut script_sig = vec![0x00, 0x48];
+        script_sig.extend([0x30; 0x48]);
+        script_sig.extend([0x51, 0x4c, 0x02, 0xAB, 0xCD]);
+        assert!(Script::new(script_sig).is_push_only());
+        assert!(Script::new(vec![]).is_push_only());
+
+        assert!(!Script::new(vec![0x01, 0xAA, 0x76]).is_push_only());
+        assert!(!Script::new(vec![0x02, 0xAA]).is_push_only());
+    }
+
+    #[test]
+    fn test_from_bytes_diagnostic_offsets() {
+        let input = TransactionInput::new(
+            OutPoint::new(dummy_txid(1), 0),
+            Script::new(vec![0x01, 0x02]),
+            0xFFFFFFFF,
+        );
+        let tx = BitcoinTransaction::new(2, vec![input.clone(), input.clone()], 7);
+        let bytes = tx.to_bytes();
+        let input_len = input.to_bytes().len();
+
+        assert_eq!(
+            BitcoinTransaction::from_bytes_diagnostic(&bytes),
+            Ok((tx, bytes.len()))
+        );
+        assert_eq!(
+            BitcoinTransaction::from_bytes_diagnostic(&bytes[..2]),
+            Err((BitcoinError::InsufficientBytes, 0))
+        );
+        assert_eq!(
+            BitcoinTransaction::from_bytes_diagnostic(&bytes[..4]),
+            Err((BitcoinError::InsufficientBytes, 4))
+        );
+        // Truncated inside the second input
+        assert_eq!(
+            BitcoinTransaction::from_bytes_diagnostic(&bytes[..5 + input_len + 10]),
+            Err((BitcoinError::InsufficientBytes, 5 + input_len))
+        );
+        // Missing lock_time
+        assert_eq!(
+            BitcoinTransaction::from_bytes_diagnostic(&bytes[..bytes.len() - 1]),
+            Err((BitcoinError::InsufficientBytes, 5 + 2 * input_len))
+        );
+    }
+
+    #[test]
+    fn test_sigop_count() {
+        // 2-of-3 multisig: OP_2 <pk> <pk> <pk> OP_3 OP_CHECKMULTISIG
+        let mut multisig = vec![0x52];
+        for _ in 0..3 {
+            multisig.push(0x21);
+            multisig.extend([0x02; 0x21]);
+        }
+        multisig.extend([0x53, 0xAE]);
+        let multisig = Script::new(multisig);
+        assert_eq!(multisig.sigop_count(false), 20);
+        assert_eq!(multisig.sigop_count(true), 3);
+
+        let checksigs = Script::new(vec![0x76, 0xAC, 0xAD, 0x00, 0xAF]);
+        assert_eq!(checksigs.sigop_count(false), 22);
+        assert_eq!(checksigs.sigop_count(true), 22);
+
+        let inputs = vec![
+            TransactionInput::new(OutPoint::new(dummy_txid(1), 0), multisig, 0),
+            TransactionInput::new(OutPoint::new(dummy_txid(2), 0), checksigs, 0),
+        ];
+        let tx = BitcoinTransaction::new(1, inputs, 0);
+        assert_eq!(tx.sigop_count(), 42);
+    }
+
+    #[test]
+    fn test_script_witness_program() {
+        let mut p2wpkh = vec![0x00, 0x14];
+        p2wpkh.extend([0xAA; 20]);
+        assert_eq!(
+            Script::new(p2wpkh).witness_program(),
+            Some((0, &[0xAA; 20][..]))
+        );
+
+        let mut p2wsh = vec![0x00, 0x20];
+        p2wsh.extend([0xBB; 32]);
+        assert_eq!(
+            Script::new(p2wsh).witness_program(),
+            Some((0, &[0xBB; 32][..]))
+        );
+
+        let mut p2tr = vec![0x51, 0x20];
+        p2tr.extend([0xCC; 32]);
+        assert_eq!(
+            Script::new(p2tr).witness_program(),
+            Some((1, &[0xCC; 32][..]))
+        );
+
+        let mut p2pkh = vec![0x76, 0xA9, 0x14];
+        p2pkh.extend([0x11; 20]);
+        p2pkh.extend([0x88, 0xAC]);
+        assert_eq!(Script::new(p2pkh).witness_program(), None);
+        assert_eq!(Script::new(vec![0x00, 0x01, 0xAA]).witness_program(), None);
+        assert_eq!(Script::new(vec![0x00, 0x14, 0xAA]).witness_program(), None);
+    }
+
+    #[test]
+    fn test_compact_size_from_bytes_max() {
+        let bytes = CompactSize::new(10_000).to_bytes();
+        assert_eq!(
+            CompactSize::from_bytes_max(&bytes, 10_000),
+            Ok((CompactSize::new(10_000), 3))
+        );
+        assert_eq!(
+            CompactSize::from_bytes_max(&bytes, 9_999),
+            Err(BitcoinError::InvalidFormat)
+        );
+        assert_eq!(
+            CompactSize::from_bytes_max(&[0xFE, 0x00], 10),
+            Err(BitcoinError::InsufficientBytes)
+        );
+    }
+
+    #[test]
+    fn test_vout_and_sequence_newtypes() {
+        let input = TransactionInput::new(
+            OutPoint::new(dummy_txid(1), 3),
+            Script::new(vec![]),
+            0xFFFFFFFD,
+        );
+        assert_eq!(input.previous_output.vout(), Vout(3));
+        assert_eq!(u32::from(input.previous_output.vout()), 3);
+        assert_eq!(Vout::from(7u32), Vout(7));
+
+        let sequence = input.sequence();
+        assert_eq!(u32::from(sequence), 0xFFFFFFFD);
+        assert!(sequence.enables_rbf());
+        assert!(!sequence.is_final());
+        assert_eq!(sequence.relative_locktime(), None);
+
+        assert!(Sequence::MAX.is_final());
+        assert!(!Sequence::from(0xFFFFFFFE).enables_rbf());
+        assert_eq!(
+            Sequence(144).relative_locktime(),
+            Some(RelativeLockTime::Blocks(144))
+        );
+        assert_eq!(
+            Sequence((1 << 22) | 10).relative_locktime(),
+            Some(RelativeLockTime::Time(10))
+        );
+    }
+
+    #[test]
+    fn test_script_asm_roundtrip() {
+        let hash = "89abcdefabbaabbaabbaabbaabbaabbaabbaabba";
+        let asm = format!("OP_DUP OP_HASH160 {} OP_EQUALVERIFY OP_CHECKSIG", hash);
+        let script = Script::from_asm(&asm).unwrap();
+
+        let mut expected = vec![0x76, 0xA9, 0x14];
+        expected.extend(hex::decode(hash).unwrap());
+        expected.extend([0x88, 0xAC]);
+        assert_eq!(script.bytes, expected);
+        assert_eq!(script.to_asm(), asm);
+
+        assert_eq!(
+            Script::from_asm("OP_0 05 OP_2 OP_CHECKMULTISIG")
+                .unwrap()
+                .bytes,
+            vec![0x00, 0x55, 0x52, 0xAE]
+        );
+        assert_eq!(Script::new(vec![0x4c, 0x05, 0x01]).to_asm(), "[error]");
+        assert_eq!(
+            Script::from_asm("OP_BOGUS"),
+            Err(BitcoinError::InvalidFormat)
+        );
+        assert_eq!(Script::from_asm("zz"), Err(BitcoinError::InvalidFormat));
+    }
+
+    #[test]
+    fn test_canonical_json_string() {
+        let input = TransactionInput::new(
+            OutPoint::new(dummy_txid(0x01), 1),
+            Script::new(vec![0xAB]),
+            0xFFFFFFFF,
+        );
+        let tx = BitcoinTransaction::new(2, vec![input], 0);
+        let expected = format!(
+            "{{\"version\":2,\"locktime\":0,\"vin\":[{{\"txid\":\"01{}\",\"vout\":1,\
+             \"scriptSig\":{{\"hex\":\"ab\"}},\"sequence\":4294967295}}]}}",
+            "00".repeat(31)
+        );
+        assert_eq!(tx.to_canonical_json_string(), expected);
+        assert_eq!(
+            tx.to_canonical_json_string(),
+            tx.clone().to_canonical_json_string()
+        );
+    }
+
+    #[test]
+    fn test_from_bytes_lenient_ignores_trailing_data() {
+        let tx = BitcoinTransaction::new(
+            2,
+            vec![TransactionInput::new(
+                OutPoint::new(dummy_txid(1), 0),
+                Script::new(vec![0x51]),
+                0xFFFFFFFF,
+            )],
+            0,
+        );
+        let mut bytes = tx.to_bytes();
+        let len = bytes.len();
+        bytes.extend([0xDE, 0xAD]);
+
+        assert_eq!(
+            BitcoinTransaction::from_bytes_lenient(&bytes),
+            Ok((tx.clone(), len))
+        );
+        assert_eq!(
+            BitcoinTransaction::from_bytes_exact(&bytes),
+            Err(BitcoinError::InvalidFormat)
+        );
+        assert_eq!(BitcoinTransaction::from_bytes_exact(&bytes[..len]), Ok(tx));
+    }
+
+    #[test]
+    fn test_remap_prevouts() {
+        let inputs = (1..=3)
+            .map(|i| TransactionInput::new(OutPoint::new(dummy_txid(i), 0), Script::new(vec![]), 0))
+            .collect();
+        let mut tx = BitcoinTransaction::new(2, inputs, 0);
+        let original = tx.clone();
+
+        let mut mapping = HashMap::new();
+        mapping.insert(
+            OutPoint::new(dummy_txid(2), 0),
+            OutPoint::new(dummy_txid(0x20), 5),
+        );
+        mapping.insert(
+            OutPoint::new(dummy_txid(9), 0),
+            OutPoint::new(dummy_txid(1), 1),
+        );
+        tx.remap_prevouts(&mapping);
+
+        assert_eq!(tx.inputs[0], original.inputs[0]);
+        assert_eq!(
+            tx.inputs[1].previous_output,
+            OutPoint::new(dummy_txid(0x20), 5)
+        );
+        assert_eq!(tx.inputs[1].sequence, original.inputs[1].sequence);
+        assert_eq!(tx.inputs[2], original.inputs[2]);
+        assert_ne!(tx.txid(), original.txid());
+    }
+
+    mod compact_size_boundaries {
+        use super::*;
+
+        fn assert_encoding(value: u64, len: usize, first: u8) {
+            let bytes = CompactSize::new(value).to_bytes();
+            assert_eq!(bytes.len(), len, "length for {}", value);
+            assert_eq!(bytes[0], first, "prefix for {}", value);
+            assert_eq!(
+                CompactSize::from_bytes(&bytes),
+                Ok((CompactSize::new(value), len))
+            );
+        }
+
+        #[test]
+        fn single_byte_to_fd() {
+            assert_encoding(0xFB, 1, 0xFB);
+            assert_encoding(0xFC, 1, 0xFC);
+            assert_encoding(0xFD, 3, 0xFD);
+        }
+
+        #[test]
+        fn fd_to_fe() {
+            assert_encoding(0xFFFE, 3, 0xFD);
+            assert_encoding(0xFFFF, 3, 0xFD);
+            assert_encoding(0x1_0000, 5, 0xFE);
+        }
+
+        #[test]
+        fn fe_to_ff() {
+            assert_encoding(0xFFFF_FFFE, 5, 0xFE);
+            assert_encoding(0xFFFF_FFFF, 5, 0xFE);
+            assert_encoding(0x1_0000_0000, 9, 0xFF);
+        }
+    }
+
+    #[test]
+    fn test_script_pubkey_hash() {
+        let hash20 = [0x11; 20];
+        let hash32 = [0x22; 32];
+        let script = |prefix: &[u8], hash: &[u8], suffix: &[u8]| {
+            Script::new([prefix, hash, suffix].concat())
+        };
+
+        let p2pkh = script(&[0x76, 0xA9, 0x14], &hash20, &[0x88, 0xAC]);
+        let p2sh = script(&[0xA9, 0x14], &hash20, &[0x87]);
+        let p2wpkh = script(&[0x00, 0x14], &hash20, &[]);
+        let p2wsh = script(&[0x00, 0x20], &hash32, &[]);
+        let p2tr = script(&[0x51, 0x20], &hash32, &[]);
+        for s in [&p2pkh, &p2sh, &p2wpkh] {
+            assert_eq!(s.pubkey_hash(), Some(hash20.to_vec()));
+        }
+        for s in [&p2wsh, &p2tr] {
+            assert_eq!(s.pubkey_hash(), Some(hash32.to_vec()));
+        }
+
+        assert_eq!(script(&[0x6A, 0x14], &hash20, &[]).pubkey_hash(), None);
+        assert_eq!(script(&[0x51, 0x14], &hash20, &[]).pubkey_hash(), None);
+        assert_eq!(
+            script(&[0x76, 0xA9, 0x14], &hash20, &[0x88]).pubkey_hash(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_compact_size_checked_add() {
+        assert_eq!(
+            CompactSize::new(2).checked_add(3),
+            Some(CompactSize::new(5))
+        );
+        assert_eq!(
+            CompactSize::new(u64::MAX - 1).checked_add(1),
+            Some(CompactSize::new(u64::MAX))
+        );
+        assert_eq!(CompactSize::new(u64::MAX).checked_add(1), None);
+    }
+
+    #[test]
+    fn test_parse_tx_message() {
+        let tx = BitcoinTransaction::new(
+            1,
+            vec![TransactionInput::new(
+                OutPoint::new(dummy_txid(1), 0),
+                Script::new(vec![0x51]),
+                0xFFFFFFFF,
+            )],
+            0,
+        );
+        let payload = tx.to_bytes();
+        let mut message = vec![0xF9, 0xBE, 0xB4, 0xD9];
+        message.extend(b"tx\0\0\0\0\0\0\0\0\0\0");
+        message.extend((payload.len() as u32).to_le_bytes());
+        message.extend(&hashes::sha256d(&payload)[..4]);
+        message.extend(&payload);
+
+        assert_eq!(
+            p2p::parse_tx_message(&message, p2p::Network::Mainnet),
+            Ok(tx)
+        );
+        assert_eq!(
+            p2p::parse_tx_message(&message, p2p::Network::Testnet),
+            Err(BitcoinError::InvalidFormat)
+        );
+        assert_eq!(
+            p2p::parse_tx_message(&message[..message.len() - 1], p2p::Network::Mainnet),
+            Err(BitcoinError::InsufficientBytes)
+        );
+
+        let mut bad_checksum = message.clone();
+        bad_checksum[20] ^= 0xFF;
+        assert_eq!(
+            p2p::parse_tx_message(&bad_checksum, p2p::Network::Mainnet),
+            Err(BitcoinError::InvalidFormat)
+        );
+
+        let mut wrong_command = message.clone();
+        wrong_command[4..6].copy_from_slice(b"tz");
+        assert_eq!(
+            p2p::parse_tx_message(&wrong_command, p2p::Network::Mainnet),
+            Err(BitcoinError::InvalidFormat)
+        );
+    }
+
+    #[test]
+    fn test_encode_message_roundtrip() {
+        let tx = BitcoinTransaction::new(
+            2,
+            vec![TransactionInput::new(
+                OutPoint::new(dummy_txid(7), 1),
+                Script::new(vec![0x00, 0x51]),
+                0xFFFFFFFD,
+            )],
+            100,
+        );
+        let message = p2p::encode_message("tx", &tx.to_bytes(), p2p::Network::Regtest).unwrap();
+        assert_eq!(message.len(), p2p::HEADER_LEN + tx.to_bytes().len());
+        assert_eq!(&message[4..16], b"tx\0\0\0\0\0\0\0\0\0\0");
+        assert_eq!(
+            p2p::parse_tx_message(&message, p2p::Network::Regtest),
+            Ok(tx)
+        );
+
+        // Empty payloads carry the checksum of the empty string
+        let verack = p2p::encode_message("verack", &[], p2p::Network::Mainnet).unwrap();
+        assert_eq!(&verack[20..24], &[0x5D, 0xF6, 0xE0, 0xE2]);
+
+        assert_eq!(
+            p2p::encode_message("thirteenchars", &[], p2p::Network::Mainnet),
+            Err(BitcoinError::InvalidFormat)
+        );
+        assert_eq!(
+            p2p::encode_message("tx\u{e9}", &[], p2p::Network::Mainnet),
+            Err(BitcoinError::InvalidFormat)
+        );
+    }
+
+    #[test]
+    fn test_inv_roundtrip() {
+        assert_eq!(p2p::encode_inv(&[]), vec![0x00]);
+        assert_eq!(p2p::parse_inv(&[0x00]), Ok(vec![]));
+
+        let entries = vec![
+            (1, dummy_txid(0xAA)),
+            (2, dummy_txid(0xBB)),
+            (0x40000001, [0xCC; 32]),
+        ];
+        let bytes = p2p::encode_inv(&entries);
+        assert_eq!(bytes.len(), 1 + 3 * 36);
+        assert_eq!(&bytes[1..5], &[0x01, 0x00, 0x00, 0x00]);
+        assert_eq!(p2p::parse_inv(&bytes), Ok(entries));
+
+        assert_eq!(
+            p2p::parse_inv(&bytes[..bytes.len() - 1]),
+            Err(BitcoinError::InsufficientBytes)
+        );
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert_eq!(p2p::parse_inv(&trailing), Err(BitcoinError::InvalidFormat));
+        assert_eq!(p2p::parse_inv(&[]), Err(BitcoinError::InsufficientBytes));
+    }
+
+    #[test]
+    fn test_uses_csv() {
+        let inputs = vec![
+            TransactionInput::new(
+                OutPoint::new(dummy_txid(1), 0),
+                Script::new(vec![]),
+                0xFFFFFFFF,
+            ),
+            TransactionInput::new(OutPoint::new(dummy_txid(2), 0), Script::new(vec![]), 144),
+        ];
+        let tx = BitcoinTransaction::new(2, inputs, 0);
+        assert!(tx.uses_csv());
+        assert!(!tx.with_version(1).uses_csv());
+
+        let mut disabled = tx.clone();
+        disabled.inputs[1].sequence = 0xFFFFFFFE;
+        assert!(!disabled.uses_csv());
+    }
+
+    #[test]
+    fn test_empty_transaction_roundtrip() {
+        // version + zero input count + lock_time; this crate has no output count
+        let tx = BitcoinTransaction::new(1, vec![], 0);
+        let bytes = tx.to_bytes();
+        assert_eq!(
+            bytes,
+            vec![0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]
+        );
+        assert_eq!(BitcoinTransaction::from_bytes(&bytes), Ok((tx, 9)));
+
+        assert_eq!(
+            BitcoinTransaction::from_bytes(&bytes[..4]),
+            Err(BitcoinError::InsufficientBytes)
+        );
+    }
+
+    #[test]
+    fn test_normalized_txid() {
+        let mut sig = vec![0x47];
+        sig.extend([0x30; 0x47]);
+        let tx = BitcoinTransaction::new(
+            1,
+            vec![TransactionInput::new(
+                OutPoint::new(dummy_txid(1), 0),
+                Script::new(sig.clone()),
+                0xFFFFFFFF,
+            )],
+            0,
+        );
+
+        // Same signature pushed non-minimally
+        let mut padded = vec![0x4c, 0x47];
+        padded.extend([0x30; 0x47]);
+        let mut malleated = tx.clone();
+        malleated.inputs[0].script_sig = Script::new(padded);
+
+        assert_ne!(tx.txid(), malleated.txid());
+        assert_eq!(tx.normalized_txid(), malleated.normalized_txid());
+        assert_ne!(tx.normalized_txid(), tx.with_lock_time(1).normalized_txid());
+    }
+
+    #[test]
+    fn test_var_str_roundtrip() {
+        let agent = "/Satoshi:27.0.0/";
+        let bytes = codec::write_var_str(agent);
+        assert_eq!(bytes[0] as usize, agent.len());
+        assert_eq!(
+            codec::read_var_str(&bytes),
+            Ok((agent.to_string(), bytes.len()))
+        );
+
+        assert_eq!(codec::read_var_str(&[0x00]), Ok((String::new(), 1)));
+        assert_eq!(
+            codec::read_var_str(&bytes[..5]),
+            Err(BitcoinError::InsufficientBytes)
+        );
+        assert_eq!(
+            codec::read_var_str(&[0x02, 0xC3, 0x28]),
+            Err(BitcoinError::InvalidFormat)
+        );
+    }
+
+    #[test]
+    fn test_opcode_names() {
+        use rust_week_3_exercises::opcodes::*;
+
+        assert_eq!(OP_DUP, 0x76);
+        assert_eq!(OP_CHECKSIG, 0xAC);
+        assert_eq!(opcode_name(OP_HASH160), "OP_HASH160");
+        assert_eq!(opcode_name(OP_PUSHDATA2), "OP_PUSHDATA2");
+        assert_eq!(opcode_name(OP_16), "OP_16");
+        assert_eq!(opcode_name(OP_TRUE), "OP_1");
+        assert_eq!(opcode_name(0x14), "OP_UNKNOWN");
+        assert_eq!(opcode_name(0xFE), "OP_UNKNOWN");
+        assert_eq!(opcode_from_name("OP_CHECKSEQUENCEVERIFY"), Some(OP_NOP3));
+        assert_eq!(opcode_from_name("OP_NOPE"), None);
+
+        // Aliases parse but names stay canonical.
+        assert_eq!(opcode_from_name("OP_FALSE"), Some(OP_0));
+        assert_eq!(opcode_from_name("OP_TRUE"), Some(OP_1));
+        assert_eq!(opcode_from_name("OP_NOP2"), Some(OP_CHECKLOCKTIMEVERIFY));
+        assert_eq!(opcode_from_name("OP_NOP3"), Some(OP_CHECKSEQUENCEVERIFY));
+        assert_eq!(opcode_name(OP_NOP2), "OP_CHECKLOCKTIMEVERIFY");
+        assert_eq!(
+            Script::from_asm("OP_FALSE OP_TRUE OP_NOP2 OP_NOP3")
+                .unwrap()
+                .to_asm(),
+            "OP_0 OP_1 OP_CHECKLOCKTIMEVERIFY OP_CHECKSEQUENCEVERIFY"
+        );
+
+        // Pushes are written as hex; a bare PUSHDATA opcode would be malformed.
+        for name in ["OP_PUSHDATA1", "OP_PUSHDATA2", "OP_PUSHDATA4"] {
+            assert_eq!(
+                Script::from_asm(&format!("OP_DUP {}", name)),
+                Err(BitcoinError::InvalidFormat)
+            );
+        }
+        assert_eq!(opcode_from_name("OP_PUSHDATA1"), Some(OP_PUSHDATA1));
+    }
+
+    #[test]
+    fn test_script_extract_signatures() {
+        // r = 1, s = 1 with SIGHASH_ALL: the smallest structurally valid signature
+        let sig_all = vec![0x30, 0x06, 0x02, 0x01, 0x01, 0x02, 0x01, 0x01, 0x01];
+        let mut sig_single_acp = sig_all.clone();
+        *sig_single_acp.last_mut().unwrap() = 0x83;
+        let pubkey = [0x02; 33];
+
+        let mut p2pkh = vec![sig_all.len() as u8];
+        p2pkh.extend(&sig_all);
+        p2pkh.push(0x21);
+        p2pkh.extend(pubkey);
+        assert_eq!(
+            Script::new(p2pkh).extract_signatures(),
+            vec![sig_all.clone()]
+        );
+
+        let mut multisig = vec![0x00];
+        for sig in [&sig_all, &sig_single_acp] {
+            multisig.push(sig.len() as u8);
+            multisig.extend(sig);
+        }
+        assert_eq!(
+            Script::new(multisig).extract_signatures(),
+            vec![sig_all.clone(), sig_single_acp]
+        );
+
+        let mut not_push_only = vec![sig_all.len() as u8];
+        not_push_only.extend(&sig_all);
+        not_push_only.push(0x76);
+        assert!(Script::new(not_push_only).extract_signatures().is_empty());
+        assert!(Script::new(vec![0x21; 34]).extract_signatures().is_empty());
+    }
+
+    #[test]
+    fn test_is_valid_der_signature() {
+        use rust_week_3_exercises::signatures::is_valid_der_signature;
+
+        // Signature from the scriptSig of the transaction in block 170 (f4184fc5...)
+        let real = hex::decode(
+            "304402204e45e16932b8af514961a1d3a1a25fdf3f4f7732e9d624c6c61548ab5fb8cd41\
+             0220181522ec8eca07de4860a4acdd12909d831cc56cbbac4622082221a8768d1d0901",
+        )
+        .unwrap();
+        assert!(is_valid_der_signature(&real));
+
+        let minimal = [0x30, 0x06, 0x02, 0x01, 0x01, 0x02, 0x01, 0x01, 0x01];
+        assert!(is_valid_der_signature(&minimal));
+
+        let mut wrong_tag = minimal;
+        wrong_tag[0] = 0x31;
+        assert!(!is_valid_der_signature(&wrong_tag));
+
+        let mut wrong_len = minimal;
+        wrong_len[1] = 0x07;
+        assert!(!is_valid_der_signature(&wrong_len));
+
+        let mut negative_r = minimal;
+        negative_r[4] = 0x81;
+        assert!(!is_valid_der_signature(&negative_r));
+
+        // R padded with an unnecessary zero byte
+        let overlong = [0x30, 0x07, 0x02, 0x02, 0x00, 0x01, 0x02, 0x01, 0x01, 0x01];
+        assert!(!is_valid_der_signature(&overlong));
+        // A zero byte is required before a value with the high bit set
+        let padded = [0x30, 0x07, 0x02, 0x02, 0x00, 0x81, 0x02, 0x01, 0x01, 0x01];
+        assert!(is_valid_der_signature(&padded));
+
+        let empty_s = [0x30, 0x06, 0x02, 0x02, 0x01, 0x01, 0x02, 0x00, 0x01];
+        assert!(!is_valid_der_signature(&empty_s));
+        assert!(!is_valid_der_signature(&minimal[..8]));
+    }
+
+    #[test]
+    fn test_input_bytes() {
+        let inputs = vec![
+            TransactionInput::new(OutPoint::new(dummy_txid(1), 0), Script::new(vec![0xAA]), 1),
+            TransactionInput::new(OutPoint::new(dummy_txid(2), 7), Script::new(vec![]), 2),
+        ];
+        let tx = BitcoinTransaction::new(2, inputs.clone(), 0);
+
+        assert_eq!(tx.input_bytes(1), Some(inputs[1].to_bytes()));
+        assert_eq!(tx.input_bytes(2), None);
+        assert_eq!(
+            inputs[1].outpoint_bytes().to_vec(),
+            inputs[1].previous_output.to_bytes()
+        );
+
+        // The inputs appear back to back after the version and count
+        let bytes = tx.to_bytes();
+        let first = tx.input_bytes(0).unwrap();
+        assert_eq!(&bytes[5..5 + first.len()], first.as_slice());
+    }
+
+    mod compact_size_vectors {
+        use super::*;
+
+        #[test]
+        fn decodes_reference_vectors() {
+            let vectors: &[(&[u8], u64, usize)] = &[
+                (&[0x00], 0, 1),
+                (&[0x01], 1, 1),
+                (&[0xFC], 0xFC, 1),
+                (&[0xFD, 0xFD, 0x00], 0xFD, 3),
+                (&[0xFD, 0x34, 0x12], 0x1234, 3),
+                (&[0xFD, 0xFF, 0xFF], 0xFFFF, 3),
+                (&[0xFE, 0x00, 0x00, 0x01, 0x00], 0x1_0000, 5),
+                (&[0xFE, 0x78, 0x56, 0x34, 0x12], 0x1234_5678, 5),
+                (&[0xFE, 0xFF, 0xFF, 0xFF, 0xFF], 0xFFFF_FFFF, 5),
+                (
+                    &[0xFF, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00],
+                    0x1_0000_0000,
+                    9,
+                ),
+                (
+                    &[0xFF, 0xEF, 0xCD, 0xAB, 0x89, 0x67, 0x45, 0x23, 0x01],
+                    0x0123_4567_89AB_CDEF,
+                    9,
+                ),
+                (&[0xFF; 9], u64::MAX, 9),
+            ];
+            for &(bytes, value, len) in vectors {
+                let (decoded, used) = CompactSize::from_bytes(bytes).unwrap();
+                assert_eq!(decoded.value, value, "{:02x?}", bytes);
+                assert_eq!(used, len, "{:02x?}", bytes);
+                assert_eq!(decoded.to_bytes(), bytes, "{:02x?}", bytes);
+
+                // Trailing data is left for the caller
+                let mut extended = bytes.to_vec();
+                extended.push(0xAA);
+                assert_eq!(CompactSize::from_bytes(&extended), Ok((decoded, len)));
+            }
+        }
+
+        #[test]
+        fn rejects_short_buffers() {
+            let short: &[&[u8]] = &[
+                &[],
+                &[0xFD],
+                &[0xFD, 0x00],
+                &[0xFE],
+                &[0xFE, 0x00, 0x00, 0x00],
+                &[0xFF],
+                &[0xFF, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00],
+            ];
+            for &bytes in short {
+                assert_eq!(
+                    CompactSize::from_bytes(bytes),
+                    Err(BitcoinError::InsufficientBytes),
+                    "{:02x?}",
+                    bytes
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_equivalent_unordered() {
+        let a = TransactionInput::new(OutPoint::new(dummy_txid(1), 0), Script::new(vec![]), 0);
+        let b = TransactionInput::new(OutPoint::new(dummy_txid(2), 0), Script::new(vec![]), 0);
+        let tx = BitcoinTransaction::new(2, vec![a.clone(), b.clone()], 0);
+        let reordered = BitcoinTransaction::new(2, vec![b.clone(), a.clone()], 0);
+
+        assert!(tx.equivalent_unordered(&reordered));
+        assert_ne!(tx.txid(), reordered.txid());
+
+        let duplicated = BitcoinTransaction::new(2, vec![a.clone(), a.clone()], 0);
+        assert!(!tx.equivalent_unordered(&duplicated));
+        assert!(!tx.equivalent_unordered(&reordered.with_lock_time(1)));
+        assert!(!tx.equivalent_unordered(&tx.take_inputs(1)));
+    }
+
+    #[test]
+    fn test_to_unsigned() {
+        let inputs = vec![
+            TransactionInput::new(
+                OutPoint::new(dummy_txid(1), 0),
+                Script::new(vec![0x01, 0xAA]),
+                0xFFFFFFFD,
+            ),
+            TransactionInput::new(OutPoint::new(dummy_txid(2), 3), Script::new(vec![0x51]), 7),
+        ];
+        let signed = BitcoinTransaction::new(2, inputs, 500);
+        let unsigned = signed.to_unsigned();
+
+        assert!(
+            unsigned
+                .inputs
+                .iter()
+                .all(|input| input.script_sig.is_empty())
+        );
+        assert_eq!(unsigned.spent_outpoints(), signed.spent_outpoints());
+        assert_eq!(unsigned.sequences(), signed.sequences());
+        assert_eq!(unsigned.version, signed.version);
+        assert_eq!(unsigned.lock_time, signed.lock_time);
+
+        // scriptSigs are part of the legacy txid
+        assert_ne!(unsigned.txid(), signed.txid());
+        assert_eq!(unsigned.txid(), signed.normalized_txid());
+        assert_eq!(unsigned.to_unsigned(), unsigned);
+    }
+
+    #[test]
+    fn test_sighash_type() {
+        use rust_week_3_exercises::signatures::*;
+
+        let sig = |flag: u8| vec![0x30, 0x06, 0x02, 0x01, 0x01, 0x02, 0x01, 0x01, flag];
+        let cases = [
+            (0x01, SIGHASH_ALL),
+            (0x02, SIGHASH_NONE),
+            (0x03, SIGHASH_SINGLE),
+            (0x81, SIGHASH_ALL | SIGHASH_ANYONECANPAY),
+            (0x82, SIGHASH_NONE | SIGHASH_ANYONECANPAY),
+            (0x83, SIGHASH_SINGLE | SIGHASH_ANYONECANPAY),
+        ];
+        for (byte, flag) in cases {
+            assert_eq!(sighash_type(&sig(byte)), Some(flag));
+            assert!(is_standard_sighash(flag));
+        }
+
+        assert_eq!(sighash_type(&[]), None);
+        for flag in [0x00, 0x04, 0x80, 0x84, 0x101] {
+            assert!(!is_standard_sighash(flag), "{:#x}", flag);
+        }
+    }
+
+    #[test]
+    fn test_script_code_for_signing() {
+        let p2pkh = Script::from_asm(
+            "OP_DUP OP_HASH160 89abcdefabbaabbaabbaabbaabbaabbaabbaabba OP_EQUALVERIFY OP_CHECKSIG",
+        )
+        .unwrap();
+        assert_eq!(p2pkh.script_code_for_signing(), p2pkh);
+
+        let with_separator =
+            Script::from_asm("OP_1 OP_CODESEPARATOR ab OP_CODESEPARATOR OP_CHECKSIG").unwrap();
+        assert_eq!(
+            with_separator.script_code_for_signing().to_asm(),
+            "OP_CHECKSIG"
+        );
+
+        // A 0xab byte inside push data is not a separator
+        let pushed = Script::from_asm("abab OP_CHECKSIG").unwrap();
+        assert_eq!(pushed.script_code_for_signing(), pushed);
+    }
+
+    #[test]
+    fn test_framed_roundtrip() {
+        let tx = BitcoinTransaction::new(
+            2,
+            vec![TransactionInput::new(
+                OutPoint::new(dummy_txid(4), 1),
+                Script::new(vec![0x51; 300]),
+                0xFFFFFFFF,
+            )],
+            9,
+        );
+        let framed = tx.to_framed();
+        let body_len = tx.to_bytes().len();
+        assert_eq!(framed[0], 0xFD);
+        assert_eq!(framed.len(), 3 + body_len);
+        assert_eq!(
+            BitcoinTransaction::from_framed(&framed),
+            Ok((tx.clone(), framed.len()))
+        );
+
+        let mut stream = framed.clone();
+        stream.extend(tx.with_version(3).to_framed());
+        let (_, used) = BitcoinTransaction::from_framed(&stream).unwrap();
+        let (second, _) = BitcoinTransaction::from_framed(&stream[used..]).unwrap();
+        assert_eq!(second.version, 3);
+
+        assert_eq!(
+            BitcoinTransaction::from_framed(&framed[..framed.len() - 1]),
+            Err(BitcoinError::InsufficientBytes)
+        );
+        // A frame longer than the transaction inside it
+        let mut padded = CompactSize::new(body_len as u64 + 1).to_bytes();
+        padded.extend(tx.to_bytes());
+        padded.push(0);
+        assert_eq!(
+            BitcoinTransaction::from_framed(&padded),
+            Err(BitcoinError::InvalidFormat)
+        );
+    }
+
+    #[test]
+    fn test_compact_size_bytes_are_canonical() {
+        let canonical: &[&[u8]] = &[
+            &[0xFC],
+            &[0xFD, 0xFD, 0x00],
+            &[0xFE, 0x00, 0x00, 0x01, 0x00],
+            &[0xFF, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00],
+        ];
+        for &bytes in canonical {
+            assert_eq!(
+                CompactSize::bytes_are_canonical(bytes),
+                Ok(true),
+                "{:02x?}",
+                bytes
+            );
+        }
+
+        let non_canonical: &[&[u8]] = &[
+            &[0xFD, 0xFC, 0x00],
+            &[0xFE, 0xFF, 0xFF, 0x00, 0x00],
+            &[0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x00, 0x00, 0x00, 0x00],
+        ];
+        for &bytes in non_canonical {
+            assert_eq!(
+                CompactSize::bytes_are_canonical(bytes),
+                Ok(false),
+                "{:02x?}",
+                bytes
+            );
+        }
+
+        assert_eq!(
+            CompactSize::bytes_are_canonical(&[0xFE, 0x00]),
+            Err(BitcoinError::InsufficientBytes)
+        );
+    }
+
+    #[test]
+    fn test_transaction_ref_borrows_scripts() {
+        let tx = BitcoinTransaction::new(
+            2,
+            vec![
+                TransactionInput::new(
+                    OutPoint::new(dummy_txid(1), 0),
+                    Script::new(vec![0xAB; 40]),
+                    0xFFFFFFFF,
+                ),
+                TransactionInput::new(OutPoint::new(dummy_txid(2), 1), Script::new(vec![]), 5),
+            ],
+            10,
+        );
+        let bytes = tx.to_bytes();
+        let (borrowed, used) = TransactionRef::from_bytes(&bytes).unwrap();
+        assert_eq!(used, bytes.len());
+        assert_eq!(borrowed.to_owned(), tx);
+
+        // The scriptSig points into the original buffer: version, count, outpoint, length byte
+        let script = borrowed.inputs[0].script_sig;
+        assert_eq!(script.as_ptr(), bytes[4 + 1 + 36 + 1..].as_ptr());
+        assert_eq!(script.len(), 40);
+
+        assert_eq!(
+            TransactionRef::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(BitcoinError::InsufficientBytes)
+        );
+    }
+
+    mod serde_roundtrips {
+        use super::*;
+        use serde::{Serialize, de::DeserializeOwned};
+        use std::fmt::Debug;
+
+        fn assert_roundtrip<T: Serialize + DeserializeOwned + PartialEq + Debug>(value: &T) {
+            let json = serde_json::to_string(value).unwrap();
+            assert_eq!(&serde_json::from_str::<T>(&json).unwrap(), value);
+
+            let binary = bincode::serialize(value).unwrap();
+            assert_eq!(&bincode::deserialize::<T>(&binary).unwrap(), value);
+        }
+
+        #[test]
+        fn each_type_roundtrips() {
+            let outpoint = OutPoint::new(dummy_txid(0x5A), 9);
+            let script = Script::new(vec![0x76, 0xA9]);
+            let input = TransactionInput::new(outpoint.clone(), script.clone(), 0xFFFFFFFE);
+            let tx = BitcoinTransaction::new(2, vec![input.clone()], 100);
+
+            assert_roundtrip(&CompactSize::new(0x1_0000_0000));
+            assert_roundtrip(&Txid(dummy_txid(0x5A)));
+            assert_roundtrip(&outpoint);
+            assert_roundtrip(&script);
+            assert_roundtrip(&input);
+            assert_roundtrip(&tx);
+            assert_roundtrip(&RpcTransaction::from(&tx));
+        }
+
+        #[test]
+        fn txid_is_hex_in_json() {
+            let json = serde_json::to_string(&Txid(dummy_txid(0x01))).unwrap();
+            assert_eq!(json, format!("\"{}01\"", "00".repeat(31)));
+        }
+
+        #[test]
+        fn txid_rejects_wrong_length_and_bad_hex() {
+            let short = format!("\"{}\"", "00".repeat(31));
+            assert!(serde_json::from_str::<Txid>(&short).is_err());
+            let long = format!("\"{}\"", "00".repeat(33));
+            assert!(serde_json::from_str::<Txid>(&long).is_err());
+            assert!(serde_json::from_str::<Txid>(&format!("\"{}\"", "zz".repeat(32))).is_err());
+
+            let upper = format!("\"{}\"", "AB".repeat(32));
+            assert_eq!(
+                serde_json::from_str::<Txid>(&upper).unwrap(),
+                Txid([0xAB; 32])
+            );
+        }
+    }
+
+    #[test]
+    fn test_write_display_verbosity() {
+        let short = TransactionInput::new(
+            OutPoint::new(dummy_txid(1), 0),
+            Script::new(vec![0xAB; DISPLAY_SCRIPT_BYTES]),
+            0xFFFFFFFF,
+        );
+        let long = TransactionInput::new(
+            OutPoint::new(dummy_txid(2), 1),
+            Script::new(vec![0xCD; DISPLAY_SCRIPT_BYTES + 1]),
+            0xFFFFFFFF,
+        );
+        let tx = BitcoinTransaction::new(1, vec![short, long], 0);
+
+        let mut verbose = String::new();
+        tx.write_display(&mut verbose, true).unwrap();
+        assert!(verbose.contains(&format!(
+            "ScriptSig: {}\n",
+            "ab".repeat(DISPLAY_SCRIPT_BYTES)
+        )));
+        assert!(verbose.contains(&format!(
+            "ScriptSig: {}\n",
+            "cd".repeat(DISPLAY_SCRIPT_BYTES + 1)
+        )));
+
+        let display = format!("{}", tx);
+        assert!(display.contains(&format!(
+            "ScriptSig: {}\n",
+            "ab".repeat(DISPLAY_SCRIPT_BYTES)
+        )));
+        assert!(display.contains(&format!(
+            "ScriptSig: {}...\n",
+            "cd".repeat(DISPLAY_SCRIPT_BYTES)
+        )));
+        assert!(display.contains(&format!("ScriptSig Length: {}", DISPLAY_SCRIPT_BYTES + 1)));
+    }
+
+    #[test]
+    fn test_negative_version_roundtrip() {
+        let tx = BitcoinTransaction::new(0xFFFFFFFF, vec![], 0);
+        assert_eq!(tx.version_i32(), -1);
+
+        let bytes = tx.to_bytes();
+        assert_eq!(&bytes[..4], &[0xFF, 0xFF, 0xFF, 0xFF]);
+        let (parsed, _) = BitcoinTransaction::from_bytes(&bytes).unwrap();
+        assert_eq!(parsed.version_i32(), -1);
+        assert_eq!(parsed.to_bytes(), bytes);
+
+        assert_eq!(BitcoinTransaction::new(0, vec![], 0).version_i32(), 0);
+        assert_eq!(
+            BitcoinTransaction::new(0x80000000, vec![], 0).version_i32(),
+            i32::MIN
+        );
+    }
+
+    #[test]
+    fn test_script_semantically_equal() {
+        let template =
+            Script::from_asm("OP_DUP OP_HASH160 abcd OP_EQUALVERIFY OP_CHECKSIG").unwrap();
+        let padded = Script::from_asm(
+            "OP_DUP OP_NOP OP_HASH160 abcd OP_EQUALVERIFY OP_CHECKSIG OP_NOP OP_NOP",
+        )
+        .unwrap();
+        assert!(template.semantically_equal(&padded));
+        assert!(padded.semantically_equal(&template));
+
+        // Other differences still count
+        let upgradable_nop =
+            Script::from_asm("OP_DUP OP_HASH160 abcd OP_EQUALVERIFY OP_CHECKSIG OP_NOP1").unwrap();
+        assert!(!template.semantically_equal(&upgradable_nop));
+        let different_data =
+            Script::from_asm("OP_DUP OP_HASH160 abce OP_EQUALVERIFY OP_CHECKSIG").unwrap();
+        assert!(!template.semantically_equal(&different_data));
+        let mut pushdata = template.bytes.clone();
+        pushdata.splice(2..3, [0x4c, 0x02]);
+        assert!(!template.semantically_equal(&Script::new(pushdata)));
+
+        let broken = Script::new(vec![0x4c, 0x05]);
+        assert!(broken.semantically_equal(&broken.clone()));
+        assert!(!broken.semantically_equal(&Script::new(vec![0x61, 0x4c, 0x05])));
+    }
+
+    #[test]
+    fn test_input_signing_flags() {
+        use rust_week_3_exercises::signatures::*;
+
+        let sig = |flag: u8| vec![0x30, 0x06, 0x02, 0x01, 0x01, 0x02, 0x01, 0x01, flag];
+        let mut script_sig = vec![0x00];
+        for flag in [0x01, 0x83] {
+            script_sig.push(9);
+            script_sig.extend(sig(flag));
+        }
+        let input = TransactionInput::new(
+            OutPoint::new(dummy_txid(1), 0),
+            Script::new(script_sig),
+            0xFFFFFFFF,
+        );
+        assert_eq!(
+            input.signing_flags(),
+            vec![SIGHASH_ALL, SIGHASH_SINGLE | SIGHASH_ANYONECANPAY]
+        );
+
+        let segwit = TransactionInput::new(OutPoint::new(dummy_txid(2), 0), Script::new(vec![]), 0);
+        assert!(segwit.signing_flags().is_empty());
+    }
+
+    #[test]
+    fn test_from_bytes_standard() {
+        let push_only = Script::from_asm("OP_0 3006020101020101 02aa").unwrap();
+        let mut tx = BitcoinTransaction::new(
+            1,
+            vec![TransactionInput::new(
+                OutPoint::new(dummy_txid(1), 0),
+                push_only,
+                0xFFFFFFFF,
+            )],
+            0,
+        );
+        let bytes = tx.to_bytes();
+        assert_eq!(
+            BitcoinTransaction::from_bytes_standard(&bytes),
+            Ok((tx.clone(), bytes.len()))
+        );
+
+        tx.inputs[0].script_sig = Script::from_asm("OP_1 OP_DUP").unwrap();
+        let bytes = tx.to_bytes();
+        assert!(BitcoinTransaction::from_bytes(&bytes).is_ok());
+        assert_eq!(
+            BitcoinTransaction::from_bytes_standard(&bytes),
+            Err(BitcoinError::InvalidFormat)
+        );
+    }
+
+    #[test]
+    fn test_confirmations() {
+        assert_eq!(confirmations(800_000, 800_000), 1);
+        assert_eq!(confirmations(799_999, 800_000), 2);
+        assert_eq!(confirmations(0, 800_000), 0);
+        // A height above the tip is in a block we have not seen yet.
+        assert_eq!(confirmations(800_001, 800_000), 0);
+    }
+
+    #[test]
+    fn test_tagged_hash() {
+        // BIP-341 wallet test vectors, scriptPubKey case with a single script leaf.
+        let script =
+            hex::decode("20d85a959b0290bf19bb89ed43c916be835475d013da4b362117393e25a48229b8ac")
+                .unwrap();
+        let mut leaf = vec![0xc0, script.len() as u8];
+        leaf.extend(&script);
+        let leaf_hash = hashes::tagged_hash("TapLeaf", &leaf);
+        assert_eq!(
+            hex::encode(leaf_hash),
+            "5b75adecf53548f3ec6ad7d78383bf84cc57b55a3127c72b9a2481752dd88b21"
+        );
+
+        let mut tweak_msg =
+            hex::decode("187791b6f712a8ea41c8ecdd0ee77fab3e85263b37e1ec18a3651926b3a6cf27")
+                .unwrap();
+        tweak_msg.extend(leaf_hash);
+        assert_eq!(
+            hex::encode(hashes::tagged_hash("TapTweak", &tweak_msg)),
+            "cbd8679ba636c1110ea247542cfbd964131a6be84f873f7f3b62a777528ed001"
+        );
+    }
+
+    #[test]
+    fn test_op_return_chunks() {
+        let data: Vec<u8> = (0..200u8).collect();
+        let scripts = Script::op_return_chunks(&data, MAX_OP_RETURN_DATA);
+        assert_eq!(scripts.len(), 3);
+        // 80-byte chunks need OP_PUSHDATA1; the 40-byte tail is a direct push.
+        assert_eq!(scripts[0].bytes[..3], [0x6a, 0x4c, 80]);
+        assert_eq!(scripts[2].bytes[..2], [0x6a, 40]);
+
+        let mut rebuilt = Vec::new();
+        for script in &scripts {
+            assert!(script.is_provably_unspendable());
+            let pushes: Vec<_> = script.instructions().skip(1).collect();
+            assert_eq!(pushes.len(), 1);
+            match pushes[0] {
+                Ok(Instruction::Push { data, .. }) => rebuilt.extend_from_slice(data),
+                ref other => panic!("unexpected instruction {:?}", other),
+            }
+        }
+        assert_eq!(rebuilt, data);
+
+        assert!(Script::op_return_chunks(&[], MAX_OP_RETURN_DATA).is_empty());
+        assert!(Script::op_return_chunks(&data, 0).is_empty());
+    }
+
+    #[test]
+    fn test_op_return_chunks_single_byte_tail() {
+        // A 1-byte tail must stay a data push rather than becoming OP_5.
+        let mut data = vec![0xAB; 80];
+        data.push(0x05);
+        let scripts = Script::op_return_chunks(&data, MAX_OP_RETURN_DATA);
+        assert_eq!(scripts.len(), 2);
+        assert_eq!(scripts[1].bytes, vec![0x6a, 0x01, 0x05]);
+        assert_eq!(
+            scripts[1].instructions().nth(1),
+            Some(Ok(Instruction::Push {
+                opcode: 0x01,
+                data: &[0x05]
+            }))
+        );
+
+        let scripts = Script::op_return_chunks(&[0x81], MAX_OP_RETURN_DATA);
+        assert_eq!(scripts[0].bytes, vec![0x6a, 0x01, 0x81]);
+    }
+
+    #[test]
+    fn test_from_bytes_annotated() {
+        let tx = BitcoinTransaction::new(
+            2,
+            vec![
+                TransactionInput::new(OutPoint::new(dummy_txid(1), 0), Script::new(vec![]), 0),
+                TransactionInput::new(
+                    OutPoint::new(dummy_txid(2), 1),
+                    Script::new(vec![0x51; 300]),
+                    0xFFFFFFFF,
+                ),
+            ],
+            0,
+        );
+        let mut bytes = tx.to_bytes();
+        bytes.push(0xAA); // trailing data is not counted
+        let (parsed, layout) = BitcoinTransaction::from_bytes_annotated(&bytes).unwrap();
+        assert_eq!(parsed, tx);
+        assert_eq!(layout.version, 4);
+        assert_eq!(layout.input_count, 1);
+        // outpoint + script length + script + sequence
+        assert_eq!(layout.inputs, vec![36 + 1 + 4, 36 + 3 + 300 + 4]);
+        assert_eq!(layout.lock_time, 4);
+        assert_eq!(layout.total, bytes.len() - 1);
+        assert_eq!(
+            layout.version
+                + layout.input_count
+                + layout.inputs.iter().sum::<usize>()
+                + layout.lock_time,
+            layout.total
+        );
+
+        assert_eq!(
+            BitcoinTransaction::from_bytes_annotated(&bytes[..10]),
+            Err(BitcoinError::InsufficientBytes)
+        );
+    }
+
+    #[test]
+    fn test_to_asm_empty_pushdata() {
+        // OP_PUSHDATA1 with a zero length is an empty push, not an empty token.
+        let script = Script::new(vec![0x76, 0x4c, 0x00, 0xac]);
+        assert_eq!(script.to_asm(), "OP_DUP OP_0 OP_CHECKSIG");
+        assert_eq!(
+            Script::from_asm(&script.to_asm()).unwrap().bytes,
+            vec![0x76, 0x00, 0xac]
+        );
+    }
 }